@@ -1,12 +1,13 @@
 use crate::{cusercmd, rekinect};
 use gmod::lua::{LuaReference, LUA_TNUMBER};
-use kinect::{KinectSkeleton, EXTENDED_SKELETON_BONE_COUNT, SKELETON_BONE_COUNT};
+use kinect::{Joint, KinectBodyMetrics, KinectCalibrationPose, KinectHandConfidence, KinectHandState, KinectSkeleton, KinectTrackingState};
+use std::time::Duration;
 
 static mut ORIGINAL_MOTION_SENSOR_POS: Option<LuaReference> = None;
 
 #[lua_function]
 unsafe fn start(lua: gmod::lua::State) -> i32 {
-	if let Some(kinect) = rekinect::state() {
+	if let Some(kinect) = rekinect::state().as_mut() {
 		kinect.active = true;
 	}
 
@@ -16,7 +17,7 @@ unsafe fn start(lua: gmod::lua::State) -> i32 {
 
 #[lua_function]
 unsafe fn stop(_lua: gmod::lua::State) -> i32 {
-	if let Some(kinect) = rekinect::state() {
+	if let Some(kinect) = rekinect::state().as_mut() {
 		kinect.active = false;
 	}
 
@@ -25,13 +26,13 @@ unsafe fn stop(_lua: gmod::lua::State) -> i32 {
 
 #[lua_function]
 unsafe fn is_active(lua: gmod::lua::State) -> i32 {
-	lua.push_boolean(rekinect::state().is_some_and(|kinect| kinect.active));
+	lua.push_boolean(rekinect::state().as_ref().is_some_and(|kinect| kinect.active));
 	1
 }
 
 #[lua_function]
 unsafe fn is_available(lua: gmod::lua::State) -> i32 {
-	lua.push_boolean(rekinect::state().is_some_and(|kinect| kinect.available()));
+	lua.push_boolean(rekinect::state().as_ref().is_some_and(|kinect| kinect.available()));
 	1
 }
 
@@ -48,35 +49,204 @@ unsafe fn motion_sensor_pos(lua: gmod::lua::State) -> i32 {
 		return 1;
 	}
 
-	if let Some(kinect) = rekinect::state() {
-		if kinect.active && lua.lua_type(2) == LUA_TNUMBER {
-			if let Ok(bone) = usize::try_from(lua.to_integer(2)) {
-				let mut bone_vec = None;
-				if (0..SKELETON_BONE_COUNT).contains(&bone) {
-					if let KinectSkeleton::Tracked(skeleton) | KinectSkeleton::TrackedExtended(skeleton, ..) = &kinect.skeleton {
-						bone_vec = Some(&skeleton.raw_bones()[bone]);
-					}
-				} else if (SKELETON_BONE_COUNT..SKELETON_BONE_COUNT + EXTENDED_SKELETON_BONE_COUNT).contains(&bone) {
-					if let KinectSkeleton::TrackedExtended(_, skeleton) = &kinect.skeleton {
-						bone_vec = Some(&skeleton.raw_bones()[SKELETON_BONE_COUNT - bone]);
-					}
-				}
-				if let Some(bone_vec) = bone_vec {
-					lua.get_global(lua_string!("Vector"));
-					lua.push_number(bone_vec[0] as _);
-					lua.push_number(bone_vec[1] as _);
-					lua.push_number(bone_vec[2] as _);
-					lua.call(3, 1);
-					return 1;
-				}
+	if let Some(vec) = bone_vector(lua, 2, KinectSkeleton::bone) {
+		push_vector(lua, vec);
+		return 1;
+	}
+
+	lua.get_global(lua_string!("vector_origin"));
+	1
+}
+
+/// Takes a `SENSORBONE`, returns a `SENSORBONE_TRACKING` value. They sort from least to most trustworthy, with `SYNTHESIZED` between
+/// `NOT_TRACKED` and `INFERRED`.
+#[lua_function]
+unsafe fn get_tracking_state(lua: gmod::lua::State) -> i32 {
+	if let Some(kinect) = rekinect::state().as_mut() {
+		if kinect.active && lua.lua_type(1) == LUA_TNUMBER {
+			if let Some(bone) = usize::try_from(lua.to_integer(1)).ok().and_then(Joint::from_sensor_bone) {
+				lua.push_integer(kinect.skeleton.tracking_state(bone).rank() as _);
+				return 1;
 			}
 		}
 	}
 
-	lua.get_global(lua_string!("vector_origin"));
+	lua.push_integer(KinectTrackingState::NotTracked.rank() as _);
+	1
+}
+
+/// Copies `get`'s vector for the `SENSORBONE` in argument `arg` out of the skeleton.
+///
+/// Lua errors unwind straight past Rust, so nothing may call into Lua while [`rekinect::state`] is locked or it would never unlock.
+unsafe fn bone_vector(lua: gmod::lua::State, arg: i32, get: impl Fn(&KinectSkeleton, Joint) -> Option<&[f32; 3]>) -> Option<[f32; 3]> {
+	if lua.lua_type(arg) != LUA_TNUMBER {
+		return None;
+	}
+
+	let bone = usize::try_from(lua.to_integer(arg)).ok().and_then(Joint::from_sensor_bone)?;
+	let kinect = rekinect::state();
+	let kinect = kinect.as_ref().filter(|kinect| kinect.active)?;
+	get(&kinect.skeleton, bone).copied()
+}
+
+unsafe fn push_vector(lua: gmod::lua::State, vec: [f32; 3]) {
+	lua.get_global(lua_string!("Vector"));
+	lua.push_number(vec[0] as _);
+	lua.push_number(vec[1] as _);
+	lua.push_number(vec[2] as _);
+	lua.call(3, 1);
+}
+
+/// Pushes `get`'s vector for the `SENSORBONE` in argument 1, or `vector_origin` if there isn't one.
+unsafe fn push_bone_vector(lua: gmod::lua::State, get: impl Fn(&KinectSkeleton, Joint) -> Option<&[f32; 3]>) -> i32 {
+	match bone_vector(lua, 1, get) {
+		Some(vec) => push_vector(lua, vec),
+		None => lua.get_global(lua_string!("vector_origin")),
+	}
+
+	1
+}
+
+/// Takes a `SENSORBONE`, returns how fast it's moving in the same units as `MotionSensorPos` per second.
+#[lua_function]
+unsafe fn get_velocity(lua: gmod::lua::State) -> i32 {
+	push_bone_vector(lua, KinectSkeleton::velocity)
+}
+
+/// Takes a `SENSORBONE`, returns its acceleration in the same units as `MotionSensorPos` per second squared.
+#[lua_function]
+unsafe fn get_acceleration(lua: gmod::lua::State) -> i32 {
+	push_bone_vector(lua, KinectSkeleton::acceleration)
+}
+
+/// Returns a table of the player's height, arm span and limb lengths in the same units as `MotionSensorPos`, or `nil` until enough of
+/// them has been seen.
+#[lua_function]
+unsafe fn get_body_metrics(lua: gmod::lua::State) -> i32 {
+	let Some(metrics) = rekinect::state()
+		.as_ref()
+		.filter(|kinect| kinect.active)
+		.and_then(|kinect| kinect.body_metrics())
+	else {
+		lua.push_nil();
+		return 1;
+	};
+
+	let KinectBodyMetrics {
+		height,
+		arm_span,
+		shoulder_width,
+		upper_arm,
+		forearm,
+		hand,
+		thigh,
+		shin,
+		torso,
+		neck,
+		frames,
+	} = metrics;
+
+	lua.create_table(0, 11);
+	for (name, value) in [
+		(lua_string!("height"), height),
+		(lua_string!("arm_span"), arm_span),
+		(lua_string!("shoulder_width"), shoulder_width),
+		(lua_string!("upper_arm"), upper_arm),
+		(lua_string!("forearm"), forearm),
+		(lua_string!("hand"), hand),
+		(lua_string!("thigh"), thigh),
+		(lua_string!("shin"), shin),
+		(lua_string!("torso"), torso),
+		(lua_string!("neck"), neck),
+	] {
+		lua.push_number(value as _);
+		lua.set_field(-2, name);
+	}
+	lua.push_integer(frames as _);
+	lua.set_field(-2, lua_string!("frames"));
+
+	1
+}
+
+/// Takes `SENSORBONE.HAND_LEFT` or `SENSORBONE.HAND_RIGHT`, returns a `SENSORHAND_STATE` and whether the sensor is confident about it.
+#[lua_function]
+unsafe fn get_hand_state(lua: gmod::lua::State) -> i32 {
+	if let Some(kinect) = rekinect::state().as_ref() {
+		if kinect.active && lua.lua_type(1) == LUA_TNUMBER {
+			if let Some(hand) = usize::try_from(lua.to_integer(1))
+				.ok()
+				.and_then(Joint::from_sensor_bone)
+				.and_then(|bone| kinect.skeleton.hands()?.get(bone))
+			{
+				lua.push_integer(hand.state as _);
+				lua.push_boolean(hand.confidence == KinectHandConfidence::High);
+				return 2;
+			}
+		}
+	}
+
+	lua.push_integer(KinectHandState::Unknown as _);
+	lua.push_boolean(false);
+	2
+}
+
+/// Takes an optional pose (`"T"` or `"A"`) and how many seconds to hold it for. Returns whether calibration started, and runs the
+/// `MotionSensorCalibrated` hook with whether it succeeded once it's done.
+#[lua_function]
+unsafe fn calibrate(lua: gmod::lua::State) -> i32 {
+	let pose = if lua.is_none_or_nil(1) {
+		Some(KinectCalibrationPose::default())
+	} else {
+		lua.get_string(1).as_deref().and_then(KinectCalibrationPose::from_name)
+	};
+
+	let seconds = if lua.is_none_or_nil(2) { 3.0 } else { lua.to_number(2) };
+
+	match (pose, Duration::try_from_secs_f64(seconds), rekinect::state().as_ref()) {
+		(Some(pose), Ok(duration), Some(kinect)) => {
+			kinect.calibrate(pose, duration);
+			lua.push_boolean(true);
+		}
+
+		_ => lua.push_boolean(false),
+	}
+
 	1
 }
 
+/// Returns whether calibration is in progress, and how far through it is from 0 to 1.
+#[lua_function]
+unsafe fn is_calibrating(lua: gmod::lua::State) -> i32 {
+	match rekinect::state().as_ref().and_then(|kinect| kinect.calibration_progress()) {
+		Some(progress) => {
+			lua.push_boolean(true);
+			lua.push_number(progress as _);
+		}
+
+		None => {
+			lua.push_boolean(false);
+			lua.push_number(0.0);
+		}
+	}
+
+	2
+}
+
+#[lua_function]
+unsafe fn is_calibrated(lua: gmod::lua::State) -> i32 {
+	lua.push_boolean(rekinect::state().as_ref().is_some_and(|kinect| kinect.calibrated()));
+	1
+}
+
+#[lua_function]
+unsafe fn reset_calibration(_lua: gmod::lua::State) -> i32 {
+	if let Some(kinect) = rekinect::state().as_ref() {
+		kinect.reset_calibration();
+	}
+
+	0
+}
+
 pub unsafe fn init(lua: gmod::lua::State) {
 	lua.get_global(lua_string!("motionsensor"));
 	if lua.is_nil(-1) {
@@ -102,8 +272,68 @@ pub unsafe fn init(lua: gmod::lua::State) {
 	lua.push_function(is_available);
 	lua.set_table(-3);
 
+	lua.push_string("GetTrackingState");
+	lua.push_function(get_tracking_state);
+	lua.set_table(-3);
+
+	lua.push_string("GetVelocity");
+	lua.push_function(get_velocity);
+	lua.set_table(-3);
+
+	lua.push_string("GetAcceleration");
+	lua.push_function(get_acceleration);
+	lua.set_table(-3);
+
+	lua.push_string("GetBodyMetrics");
+	lua.push_function(get_body_metrics);
+	lua.set_table(-3);
+
+	lua.push_string("GetHandState");
+	lua.push_function(get_hand_state);
+	lua.set_table(-3);
+
+	lua.push_string("Calibrate");
+	lua.push_function(calibrate);
+	lua.set_table(-3);
+
+	lua.push_string("IsCalibrating");
+	lua.push_function(is_calibrating);
+	lua.set_table(-3);
+
+	lua.push_string("IsCalibrated");
+	lua.push_function(is_calibrated);
+	lua.set_table(-3);
+
+	lua.push_string("ResetCalibration");
+	lua.push_function(reset_calibration);
+	lua.set_table(-3);
+
 	lua.pop();
 
+	lua.create_table(0, 4);
+	lua.push_integer(KinectTrackingState::NotTracked.rank() as _);
+	lua.set_field(-2, lua_string!("NOT_TRACKED"));
+	lua.push_integer(KinectTrackingState::Inferred.rank() as _);
+	lua.set_field(-2, lua_string!("INFERRED"));
+	lua.push_integer(KinectTrackingState::Tracked.rank() as _);
+	lua.set_field(-2, lua_string!("TRACKED"));
+	lua.push_integer(KinectTrackingState::Synthesized.rank() as _);
+	lua.set_field(-2, lua_string!("SYNTHESIZED"));
+	lua.set_global(lua_string!("SENSORBONE_TRACKING"));
+
+	lua.create_table(0, 5);
+	lua.push_integer(KinectHandState::Unknown as _);
+	lua.set_field(-2, lua_string!("UNKNOWN"));
+	lua.push_integer(KinectHandState::NotTracked as _);
+	lua.set_field(-2, lua_string!("NOT_TRACKED"));
+	lua.push_integer(KinectHandState::Open as _);
+	lua.set_field(-2, lua_string!("OPEN"));
+	lua.push_integer(KinectHandState::Closed as _);
+	lua.set_field(-2, lua_string!("CLOSED"));
+	lua.push_integer(KinectHandState::Lasso as _);
+	lua.set_field(-2, lua_string!("LASSO"));
+	lua.set_global(lua_string!("SENSORHAND_STATE"));
+
 	lua.get_global(lua_string!("FindMetaTable"));
 	lua.push_string("Player");
 	lua.call(1, 1);
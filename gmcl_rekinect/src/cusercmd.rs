@@ -1,5 +1,5 @@
 use crate::rekinect;
-use kinect::{KinectSkeleton, KinectSkeletonRawBones};
+use kinect::{Joint, KinectSkeleton, KinectSkeletonRawBones};
 use std::ffi::c_void;
 
 static mut SEND_EXTENDED_BONES: bool = false;
@@ -24,8 +24,29 @@ unsafe fn start_command(lua: gmod::lua::State) {
 		lua.pop();
 	}
 
-	if let Some(kinect) = rekinect::state() {
-		if let (1.., true, true, KinectSkeleton::TrackedExtended(.., extended)) = (cmd_number, SEND_EXTENDED_BONES, kinect.active, &kinect.skeleton) {
+	// The game time the command is for, so the skeleton can be sampled then rather than whenever the command happens to be created
+	let cmd_time;
+	{
+		lua.get_field(2, lua_string!("TickCount"));
+		lua.push_value(2);
+		lua.call(1, 1);
+		let tick_count = lua.to_number(-1);
+		lua.pop();
+
+		lua.get_global(lua_string!("engine"));
+		lua.get_field(-1, lua_string!("TickInterval"));
+		lua.call(0, 1);
+		cmd_time = tick_count * lua.to_number(-1);
+		lua.pop_n(2);
+	}
+
+	// Copy what's needed out of the lock, a Lua error in net.* would never release it
+	let sampled = rekinect::state()
+		.as_ref()
+		.map(|kinect| (kinect.active, kinect.sample(kinect.instant_at(cmd_time))));
+
+	if let Some((active, skeleton)) = sampled {
+		if let (1.., true, true, KinectSkeleton::TrackedExtended(.., extended)) = (cmd_number, SEND_EXTENDED_BONES, active, &skeleton) {
 			EXTENDED_BONES_CLEARED = false;
 
 			lua_stack_guard!(lua => {
@@ -53,7 +74,7 @@ unsafe fn start_command(lua: gmod::lua::State) {
 
 				lua.get_global(lua_string!("Vector"));
 
-				for bone in extended.raw_bones() {
+				for bone in Joint::EXTENDED.map(|joint| extended[joint]) {
 					lua.push_value(-2);
 
 					lua.push_value(-2);
@@ -73,7 +94,7 @@ unsafe fn start_command(lua: gmod::lua::State) {
 			});
 		}
 
-		if let KinectSkeleton::Tracked(skeleton) | KinectSkeleton::TrackedExtended(skeleton, ..) = &kinect.skeleton {
+		if let KinectSkeleton::Tracked(skeleton) | KinectSkeleton::TrackedExtended(skeleton, ..) = &skeleton {
 			set_motion_sensor_positions(cusercmd, skeleton.raw_bones().as_ptr());
 			return;
 		}
@@ -0,0 +1,193 @@
+use crate::rekinect;
+use gmod::lua::LUA_TNUMBER;
+use kinect::{Joint, KinectFilter, KinectFilterConfig, KinectHoltParameters, KinectOneEuroParameters};
+
+const FILTER: &str = "rekinect_filter";
+const HOLT_SMOOTHING: &str = "rekinect_filter_holt_smoothing";
+const HOLT_CORRECTION: &str = "rekinect_filter_holt_correction";
+const HOLT_PREDICTION: &str = "rekinect_filter_holt_prediction";
+const HOLT_JITTER_RADIUS: &str = "rekinect_filter_holt_jitter_radius";
+const HOLT_MAX_DEVIATION_RADIUS: &str = "rekinect_filter_holt_max_deviation_radius";
+const ONE_EURO_MIN_CUTOFF: &str = "rekinect_filter_oneeuro_min_cutoff";
+const ONE_EURO_BETA: &str = "rekinect_filter_oneeuro_beta";
+const ONE_EURO_DERIVATIVE_CUTOFF: &str = "rekinect_filter_oneeuro_derivative_cutoff";
+
+/// Joints set to something other than `rekinect_filter` by `motionsensor.SetJointFilter`.
+static mut JOINT_FILTERS: [Option<FilterKind>; Joint::COUNT] = [None; Joint::COUNT];
+
+#[derive(Clone, Copy)]
+enum FilterKind {
+	None,
+	Holt,
+	OneEuro,
+}
+impl FilterKind {
+	fn from_name(name: &str) -> Option<Self> {
+		match name.trim().to_ascii_lowercase().as_str() {
+			"none" => Some(Self::None),
+			"holt" => Some(Self::Holt),
+			"oneeuro" => Some(Self::OneEuro),
+			_ => None,
+		}
+	}
+}
+
+pub unsafe fn convar_number(lua: gmod::lua::State, name: &str) -> f32 {
+	lua.get_global(lua_string!("GetConVarNumber"));
+	lua.push_string(name);
+	lua.call(1, 1);
+	let number = lua.to_number(-1) as f32;
+	lua.pop();
+	number
+}
+
+unsafe fn convar_filter(lua: gmod::lua::State) -> FilterKind {
+	lua.get_global(lua_string!("GetConVarString"));
+	lua.push_string(FILTER);
+	lua.call(1, 1);
+	let kind = lua.get_string(-1).as_deref().and_then(FilterKind::from_name);
+	lua.pop();
+
+	kind.unwrap_or_else(|| {
+		log::warn!("{FILTER} should be none, holt or oneeuro");
+		FilterKind::None
+	})
+}
+
+/// Sends the filters described by the convars and `motionsensor.SetJointFilter` to the Kinect.
+unsafe fn update(lua: gmod::lua::State) {
+	let holt = KinectFilter::Holt(KinectHoltParameters {
+		smoothing: convar_number(lua, HOLT_SMOOTHING).clamp(0.0, 1.0),
+		correction: convar_number(lua, HOLT_CORRECTION).clamp(0.0, 1.0),
+		prediction: convar_number(lua, HOLT_PREDICTION).max(0.0),
+		jitter_radius: convar_number(lua, HOLT_JITTER_RADIUS).max(0.0),
+		max_deviation_radius: convar_number(lua, HOLT_MAX_DEVIATION_RADIUS).max(0.0),
+	});
+
+	let one_euro = KinectFilter::OneEuro(KinectOneEuroParameters {
+		min_cutoff: convar_number(lua, ONE_EURO_MIN_CUTOFF).max(0.0),
+		beta: convar_number(lua, ONE_EURO_BETA).max(0.0),
+		derivative_cutoff: convar_number(lua, ONE_EURO_DERIVATIVE_CUTOFF).max(0.0),
+	});
+
+	let filter = |kind| match kind {
+		FilterKind::None => KinectFilter::None,
+		FilterKind::Holt => holt,
+		FilterKind::OneEuro => one_euro,
+	};
+
+	let default = convar_filter(lua);
+	let mut config = KinectFilterConfig::new(filter(default));
+	for joint in Joint::ALL {
+		if let Some(kind) = JOINT_FILTERS[joint.sensor_bone()] {
+			config.set(joint, filter(kind));
+		}
+	}
+
+	if let Some(kinect) = rekinect::state().as_ref() {
+		kinect.set_filter(config);
+	}
+}
+
+#[lua_function]
+unsafe fn convar_changed(lua: gmod::lua::State) -> i32 {
+	update(lua);
+	0
+}
+
+/// Takes a `SENSORBONE` and `"none"`, `"holt"` or `"oneeuro"`, or `nil` to go back to `rekinect_filter`. Parameters still come from the
+/// convars.
+#[lua_function]
+unsafe fn set_joint_filter(lua: gmod::lua::State) -> i32 {
+	if lua.lua_type(1) != LUA_TNUMBER {
+		return 0;
+	}
+
+	let Some(joint) = usize::try_from(lua.to_integer(1)).ok().and_then(Joint::from_sensor_bone) else {
+		return 0;
+	};
+
+	let kind = if lua.is_none_or_nil(2) {
+		None
+	} else {
+		match lua.get_string(2).as_deref().and_then(FilterKind::from_name) {
+			Some(kind) => Some(kind),
+			None => return 0,
+		}
+	};
+
+	JOINT_FILTERS[joint.sensor_bone()] = kind;
+	update(lua);
+	0
+}
+
+pub unsafe fn init(lua: gmod::lua::State) {
+	let holt = KinectHoltParameters::default();
+	let one_euro = KinectOneEuroParameters::default();
+
+	let convars = [
+		(
+			FILTER,
+			"none".to_string(),
+			"How to smooth joints: none, holt (steadier) or oneeuro (more responsive)",
+		),
+		(
+			HOLT_SMOOTHING,
+			holt.smoothing.to_string(),
+			"From 0 to 1, higher is smoother but lags more",
+		),
+		(
+			HOLT_CORRECTION,
+			holt.correction.to_string(),
+			"From 0 to 1, lower is smoother but slower to correct",
+		),
+		(HOLT_PREDICTION, holt.prediction.to_string(), "How many frames ahead to predict"),
+		(
+			HOLT_JITTER_RADIUS,
+			holt.jitter_radius.to_string(),
+			"Movements smaller than this many metres are damped",
+		),
+		(
+			HOLT_MAX_DEVIATION_RADIUS,
+			holt.max_deviation_radius.to_string(),
+			"How many metres joints may stray from the raw data",
+		),
+		(
+			ONE_EURO_MIN_CUTOFF,
+			one_euro.min_cutoff.to_string(),
+			"Cutoff frequency while still, lower is smoother but lags more",
+		),
+		(ONE_EURO_BETA, one_euro.beta.to_string(), "Higher lags less when moving quickly"),
+		(
+			ONE_EURO_DERIVATIVE_CUTOFF,
+			one_euro.derivative_cutoff.to_string(),
+			"Cutoff frequency for smoothing speed",
+		),
+	];
+
+	for (name, default, help) in convars {
+		lua.get_global(lua_string!("CreateClientConVar"));
+		lua.push_string(name);
+		lua.push_string(&default);
+		lua.push_boolean(true);
+		lua.push_boolean(false);
+		lua.push_string(help);
+		lua.call(5, 0);
+
+		lua.get_global(lua_string!("cvars"));
+		lua.get_field(-1, lua_string!("AddChangeCallback"));
+		lua.push_string(name);
+		lua.push_function(convar_changed);
+		lua.push_string("gmcl_rekinect");
+		lua.call(3, 0);
+		lua.pop();
+	}
+
+	lua.get_global(lua_string!("motionsensor"));
+	lua.push_string("SetJointFilter");
+	lua.push_function(set_joint_filter);
+	lua.set_table(-3);
+	lua.pop();
+
+	update(lua);
+}
@@ -0,0 +1,113 @@
+use crate::rekinect;
+use gmod::lua::LUA_TNUMBER;
+use kinect::Joint;
+
+/// Gesture names become file names, so keep them simple.
+fn valid_name(name: &str) -> bool {
+	!name.is_empty() && name.len() <= 64 && name.chars().all(|char| char.is_ascii_alphanumeric() || char == '_' || char == '-')
+}
+
+/// Takes the `SENSORBONE`s the gesture is made with and starts recording the player's motion. Returns whether recording started.
+#[lua_function]
+unsafe fn record_gesture(lua: gmod::lua::State) -> i32 {
+	let mut joints = Vec::new();
+
+	for arg in 1..=lua.get_top() {
+		if lua.lua_type(arg) != LUA_TNUMBER {
+			lua.push_boolean(false);
+			return 1;
+		}
+
+		match usize::try_from(lua.to_integer(arg)).ok().and_then(Joint::from_sensor_bone) {
+			Some(joint) if !joints.contains(&joint) => joints.push(joint),
+			Some(_) => {}
+			None => {
+				lua.push_boolean(false);
+				return 1;
+			}
+		}
+	}
+
+	match rekinect::state().as_mut() {
+		Some(kinect) if !joints.is_empty() => {
+			kinect.record_gesture(joints);
+			lua.push_boolean(true);
+		}
+
+		_ => lua.push_boolean(false),
+	}
+
+	1
+}
+
+/// Takes a name made of letters, digits, `_` and `-`, and saves what was recorded under it. Returns whether anything was being recorded.
+///
+/// From then on, the `MotionSensorGesture` hook runs with the name and a confidence from 0 to 1 whenever the player performs it.
+#[lua_function]
+unsafe fn finish_gesture(lua: gmod::lua::State) -> i32 {
+	let name = lua.get_string(1).map(|name| name.into_owned()).filter(|name| valid_name(name));
+
+	match (name, rekinect::state().as_mut()) {
+		(Some(name), Some(kinect)) => lua.push_boolean(kinect.finish_gesture(name)),
+		_ => lua.push_boolean(false),
+	}
+
+	1
+}
+
+#[lua_function]
+unsafe fn remove_gesture(lua: gmod::lua::State) -> i32 {
+	let name = lua.get_string(1).map(|name| name.into_owned()).filter(|name| valid_name(name));
+
+	if let (Some(name), Some(kinect)) = (name, rekinect::state().as_ref()) {
+		kinect.remove_gesture(name);
+	}
+
+	0
+}
+
+/// Reads `garrysmod/data/rekinect/rules.txt` again after it was edited. Returns whether it could be read, and why not if it couldn't.
+///
+/// Poses run the `MotionSensorPose` hook with their name and whether they're now held, and sequences run `MotionSensorGesture`.
+#[lua_function]
+unsafe fn reload_rules(lua: gmod::lua::State) -> i32 {
+	match rekinect::state().as_ref().map(|kinect| kinect.reload_rules()) {
+		Some(Ok(())) => {
+			lua.push_boolean(true);
+			1
+		}
+
+		Some(Err(err)) => {
+			lua.push_boolean(false);
+			lua.push_string(&err.to_string());
+			2
+		}
+
+		None => {
+			lua.push_boolean(false);
+			1
+		}
+	}
+}
+
+pub unsafe fn init(lua: gmod::lua::State) {
+	lua.get_global(lua_string!("motionsensor"));
+
+	lua.push_string("RecordGesture");
+	lua.push_function(record_gesture);
+	lua.set_table(-3);
+
+	lua.push_string("FinishGesture");
+	lua.push_function(finish_gesture);
+	lua.set_table(-3);
+
+	lua.push_string("RemoveGesture");
+	lua.push_function(remove_gesture);
+	lua.set_table(-3);
+
+	lua.push_string("ReloadRules");
+	lua.push_function(reload_rules);
+	lua.set_table(-3);
+
+	lua.pop();
+}
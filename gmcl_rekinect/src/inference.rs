@@ -0,0 +1,43 @@
+use crate::{filter::convar_number, rekinect};
+use kinect::KinectInference;
+
+const INFERENCE: &str = "rekinect_inference";
+
+/// Sends the inference described by the convar to the Kinect.
+unsafe fn update(lua: gmod::lua::State) {
+	let inference = match convar_number(lua, INFERENCE) as i32 {
+		1 => Some(KinectInference::default()),
+		2 => Some(KinectInference::default().with_replace_inferred(true)),
+		_ => None,
+	};
+
+	if let Some(kinect) = rekinect::state().as_ref() {
+		kinect.set_inference(inference);
+	}
+}
+
+#[lua_function]
+unsafe fn convar_changed(lua: gmod::lua::State) -> i32 {
+	update(lua);
+	0
+}
+
+pub unsafe fn init(lua: gmod::lua::State) {
+	lua.get_global(lua_string!("CreateClientConVar"));
+	lua.push_string(INFERENCE);
+	lua.push_string("0");
+	lua.push_boolean(true);
+	lua.push_boolean(false);
+	lua.push_string("Fill in joints the sensor lost: 0 never, 1 when it can't see them, 2 also when it's only guessing");
+	lua.call(5, 0);
+
+	lua.get_global(lua_string!("cvars"));
+	lua.get_field(-1, lua_string!("AddChangeCallback"));
+	lua.push_string(INFERENCE);
+	lua.push_function(convar_changed);
+	lua.push_string("gmcl_rekinect");
+	lua.call(3, 0);
+	lua.pop();
+
+	update(lua);
+}
@@ -0,0 +1,63 @@
+use crate::{filter::convar_number, rekinect};
+use kinect::KinectInterpolation;
+use std::time::Duration;
+
+const DELAY: &str = "rekinect_interpolation_delay";
+const MAX_EXTRAPOLATION: &str = "rekinect_interpolation_max_extrapolation";
+
+/// Sends the interpolation described by the convars to the Kinect.
+unsafe fn update(lua: gmod::lua::State) {
+	let milliseconds = |name| Duration::try_from_secs_f32(convar_number(lua, name).max(0.0) / 1000.0).unwrap_or_default();
+
+	let interpolation = KinectInterpolation {
+		delay: milliseconds(DELAY),
+		max_extrapolation: milliseconds(MAX_EXTRAPOLATION),
+	};
+
+	if let Some(kinect) = rekinect::state().as_ref() {
+		kinect.set_interpolation(interpolation);
+	}
+}
+
+#[lua_function]
+unsafe fn convar_changed(lua: gmod::lua::State) -> i32 {
+	update(lua);
+	0
+}
+
+pub unsafe fn init(lua: gmod::lua::State) {
+	let interpolation = KinectInterpolation::default();
+
+	let convars = [
+		(
+			DELAY,
+			interpolation.delay.as_millis().to_string(),
+			"Milliseconds to lag behind the sensor by, up to 33. Higher is smoother, lower extrapolates more",
+		),
+		(
+			MAX_EXTRAPOLATION,
+			interpolation.max_extrapolation.as_millis().to_string(),
+			"Milliseconds to predict joints ahead of the latest frame for at most",
+		),
+	];
+
+	for (name, default, help) in convars {
+		lua.get_global(lua_string!("CreateClientConVar"));
+		lua.push_string(name);
+		lua.push_string(&default);
+		lua.push_boolean(true);
+		lua.push_boolean(false);
+		lua.push_string(help);
+		lua.call(5, 0);
+
+		lua.get_global(lua_string!("cvars"));
+		lua.get_field(-1, lua_string!("AddChangeCallback"));
+		lua.push_string(name);
+		lua.push_function(convar_changed);
+		lua.push_string("gmcl_rekinect");
+		lua.call(3, 0);
+		lua.pop();
+	}
+
+	update(lua);
+}
@@ -12,7 +12,11 @@ extern crate gmod;
 mod api;
 mod cusercmd;
 mod exbones;
+mod filter;
+mod gesture;
 mod hax;
+mod inference;
+mod interpolation;
 mod logging;
 mod rekinect;
 
@@ -30,6 +34,18 @@ unsafe fn init(lua: gmod::lua::State) {
 	lua_stack_guard!(lua => {
 		rekinect::init(lua);
 	});
+	lua_stack_guard!(lua => {
+		filter::init(lua);
+	});
+	lua_stack_guard!(lua => {
+		gesture::init(lua);
+	});
+	lua_stack_guard!(lua => {
+		inference::init(lua);
+	});
+	lua_stack_guard!(lua => {
+		interpolation::init(lua);
+	});
 }
 
 unsafe fn shutdown() {
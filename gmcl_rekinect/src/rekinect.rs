@@ -1,57 +1,304 @@
 use kinect::*;
+use std::{
+	path::PathBuf,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Mutex, MutexGuard, PoisonError,
+	},
+	time::{Duration, Instant},
+};
 
-static mut INIT_REFCOUNT: usize = 0;
-static mut KINECT: Option<KinectState> = None;
+static INIT_REFCOUNT: AtomicUsize = AtomicUsize::new(0);
+static KINECT: Mutex<Option<KinectState>> = Mutex::new(None);
+
+/// Game times further than this from the last Think, in seconds, are treated as this far.
+const MAX_GAME_TIME_OFFSET: f64 = 0.25;
 
 pub struct KinectState {
-	inner: Kinect,
+	inner: KinectHandle,
 	pub active: bool,
 	pub skeleton: KinectSkeleton,
+	calibration: KinectCalibrationState,
+	recording_gesture: bool,
+	/// When the last Think happened, and `CurTime()` at the time.
+	clock: Option<(Instant, f64)>,
 }
 impl KinectState {
 	fn new() -> Result<Self, std::io::Error> {
+		let inner = KinectHandle::spawn(|| {
+			let mut kinect = Kinect::new()?;
+
+			match KinectBodyProfile::load(BODY_PROFILE_PATH) {
+				Ok(profile) => kinect.set_profile(Some(profile)),
+				Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+				Err(err) => log::warn!("{BODY_PROFILE_PATH}: {err:?}"),
+			}
+
+			match KinectRules::load(RULES_PATH) {
+				Ok(rules) => kinect.set_rules(rules),
+				Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+				Err(err) => log::warn!("{RULES_PATH}: {err:?}"),
+			}
+
+			match KinectGestureTemplate::load_dir(GESTURE_TEMPLATE_DIRECTORY) {
+				Ok(templates) => templates.into_iter().for_each(|template| kinect.add_gesture(template)),
+				Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+				Err(err) => log::warn!("{GESTURE_TEMPLATE_DIRECTORY}: {err:?}"),
+			}
+
+			Ok(kinect)
+		})?;
+
 		Ok(Self {
-			inner: Kinect::new()?,
+			inner,
 			active: false,
 			skeleton: KinectSkeleton::default(),
+			calibration: KinectCalibrationState::default(),
+			recording_gesture: false,
+			clock: None,
 		})
 	}
 
-	pub fn update(&mut self) {
+	/// Called every Think with `CurTime()`. Returns whether each calibration that finished since the last call succeeded.
+	pub fn update(&mut self, cur_time: f64) -> Vec<bool> {
+		let snapshot = self.inner.snapshot();
+
+		let now = Instant::now();
+		self.clock = Some((now, cur_time));
+
+		if self.active {
+			self.skeleton = self.inner.sample(now);
+		}
+
+		self.calibration = snapshot.calibration;
+
+		self.inner
+			.take_calibrations()
+			.into_iter()
+			.map(|calibration| {
+				if let Some(profile) = calibration.profile {
+					if let Err(err) = profile.save(BODY_PROFILE_PATH) {
+						log::error!("Failed to save {BODY_PROFILE_PATH}: {err:?}");
+					}
+				}
+
+				calibration.profile.is_some()
+			})
+			.collect()
+	}
+
+	/// The skeleton at `at`, see [`KinectHandle::sample`]. Stays as it was while inactive, like [`KinectState::skeleton`].
+	pub fn sample(&self, at: Instant) -> KinectSkeleton {
+		if self.active {
+			self.inner.sample(at)
+		} else {
+			self.skeleton
+		}
+	}
+
+	/// When `CurTime()` will be (or was) `time`, going by the last Think.
+	pub fn instant_at(&self, time: f64) -> Instant {
+		let Some((instant, cur_time)) = self.clock else {
+			return Instant::now();
+		};
+
+		let offset = (time - cur_time).clamp(-MAX_GAME_TIME_OFFSET, MAX_GAME_TIME_OFFSET);
+		if offset >= 0.0 {
+			instant + Duration::from_secs_f64(offset)
+		} else {
+			instant.checked_sub(Duration::from_secs_f64(-offset)).unwrap_or(instant)
+		}
+	}
+
+	pub fn calibrate(&self, pose: KinectCalibrationPose, duration: Duration) {
+		self.inner.run_on_thread(move |kinect| kinect.calibrate(pose, duration));
+	}
+
+	/// Stops normalising bodies and forgets the saved profile.
+	pub fn reset_calibration(&self) {
+		self.inner.run_on_thread(|kinect| kinect.set_profile(None));
+
+		match std::fs::remove_file(BODY_PROFILE_PATH) {
+			Ok(()) => {}
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+			Err(err) => log::error!("Failed to delete {BODY_PROFILE_PATH}: {err:?}"),
+		}
+	}
+
+	#[inline]
+	pub fn set_filter(&self, config: KinectFilterConfig) {
+		self.inner.run_on_thread(move |kinect| kinect.set_filter(config));
+	}
+
+	#[inline]
+	pub fn set_inference(&self, inference: Option<KinectInference>) {
+		self.inner.run_on_thread(move |kinect| kinect.set_inference(inference));
+	}
+
+	#[inline]
+	pub fn set_interpolation(&self, interpolation: KinectInterpolation) {
+		self.inner.set_interpolation(interpolation);
+	}
+
+	/// Only keeps the selected body's events, and nothing while inactive.
+	fn selected_events<T>(&self, events: Vec<T>, body: impl Fn(&T) -> KinectBodyId) -> Vec<T> {
 		if !self.active {
-			return;
+			return Vec::new();
 		}
 
-		let Some(update) = self.inner.poll() else {
-			return;
+		let selected = self.inner.snapshot().body;
+		events.into_iter().filter(|event| Some(body(event)) == selected).collect()
+	}
+
+	/// Gestures the selected body performed since the last call.
+	#[inline]
+	pub fn take_gestures(&self) -> Vec<KinectGestureEvent> {
+		self.selected_events(self.inner.take_gestures(), |gesture| gesture.body)
+	}
+
+	/// Poses the selected body struck or released since the last call.
+	#[inline]
+	pub fn take_poses(&self) -> Vec<KinectPoseEvent> {
+		self.selected_events(self.inner.take_poses(), |pose| pose.body)
+	}
+
+	/// Reads [`RULES_PATH`] again, keeping the old rules if it can't be read.
+	pub fn reload_rules(&self) -> Result<(), std::io::Error> {
+		let rules = match KinectRules::load(RULES_PATH) {
+			Ok(rules) => rules,
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => KinectRules::default(),
+			Err(err) => return Err(err),
 		};
 
-		self.skeleton = update;
+		self.inner.run_on_thread(move |kinect| kinect.set_rules(rules));
+		Ok(())
+	}
+
+	pub fn record_gesture(&mut self, joints: Vec<Joint>) {
+		self.recording_gesture = true;
+		self.inner.run_on_thread(move |kinect| kinect.record_gesture(joints));
+	}
+
+	/// Saves what was recorded as `name` and starts recognising it. Returns `false` if nothing was being recorded.
+	pub fn finish_gesture(&mut self, name: String) -> bool {
+		if !std::mem::take(&mut self.recording_gesture) {
+			return false;
+		}
+
+		self.inner.run_on_thread(move |kinect| {
+			let Some(template) = kinect.finish_gesture(name.as_str()) else {
+				log::warn!("Not enough of gesture {name} was recorded");
+				return;
+			};
+
+			match template.save(gesture_path(&name)) {
+				Ok(()) => log::info!("Recorded gesture {name}"),
+				Err(err) => log::error!("Failed to save gesture {name}: {err:?}"),
+			}
+
+			kinect.add_gesture(template);
+		});
+
+		true
+	}
+
+	/// Stops recognising `name` and deletes its template.
+	pub fn remove_gesture(&self, name: String) {
+		match std::fs::remove_file(gesture_path(&name)) {
+			Ok(()) => {}
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+			Err(err) => log::error!("Failed to delete gesture {name}: {err:?}"),
+		}
+
+		self.inner.run_on_thread(move |kinect| {
+			kinect.remove_gesture(&name);
+		});
+	}
+
+	/// The capture's progress from 0 to 1, if calibrating.
+	pub fn calibration_progress(&self) -> Option<f32> {
+		match self.calibration {
+			KinectCalibrationState::Capturing { progress, .. } => Some(progress),
+			_ => None,
+		}
+	}
+
+	/// The selected body's proportions, in the same units as `MotionSensorPos`.
+	#[inline]
+	pub fn body_metrics(&self) -> Option<KinectBodyMetrics> {
+		self.inner.snapshot().metrics
+	}
+
+	#[inline]
+	pub fn calibrated(&self) -> bool {
+		self.inner.snapshot().profile.is_some()
 	}
 
 	#[inline]
 	pub fn available(&self) -> bool {
-		self.inner.available()
+		self.inner.snapshot().available
 	}
 }
 
 #[lua_function]
-unsafe fn poll(_lua: gmod::lua::State) {
-	if let Some(kinect) = &mut KINECT {
-		kinect.update();
+unsafe fn poll(lua: gmod::lua::State) {
+	let cur_time;
+	{
+		lua.get_global(lua_string!("CurTime"));
+		lua.call(0, 1);
+		cur_time = lua.to_number(-1);
+		lua.pop();
+	}
+
+	// Release the lock before running hooks, they're likely to call back into motionsensor
+	let (calibrated, poses, gestures) = state()
+		.as_mut()
+		.map(|kinect| (kinect.update(cur_time), kinect.take_poses(), kinect.take_gestures()))
+		.unwrap_or_default();
+
+	for success in calibrated {
+		lua.get_global(lua_string!("hook"));
+		lua.get_field(-1, lua_string!("Run"));
+		lua.push_string("MotionSensorCalibrated");
+		lua.push_boolean(success);
+		lua.call(2, 0);
+		lua.pop();
+	}
+
+	for pose in poses {
+		lua.get_global(lua_string!("hook"));
+		lua.get_field(-1, lua_string!("Run"));
+		lua.push_string("MotionSensorPose");
+		lua.push_string(&pose.name);
+		lua.push_boolean(pose.held);
+		lua.call(3, 0);
+		lua.pop();
+	}
+
+	for gesture in gestures {
+		lua.get_global(lua_string!("hook"));
+		lua.get_field(-1, lua_string!("Run"));
+		lua.push_string("MotionSensorGesture");
+		lua.push_string(&gesture.name);
+		lua.push_number(gesture.confidence as _);
+		lua.call(3, 0);
+		lua.pop();
 	}
 }
 
-pub unsafe fn init(lua: gmod::lua::State) {
-	INIT_REFCOUNT += 1;
+/// Where the template for the gesture `name` is saved. The Lua API only lets through names that are safe to use as file names.
+fn gesture_path(name: &str) -> PathBuf {
+	PathBuf::from(GESTURE_TEMPLATE_DIRECTORY).join(format!("{name}.txt"))
+}
 
-	if INIT_REFCOUNT != 1 {
+pub unsafe fn init(lua: gmod::lua::State) {
+	if INIT_REFCOUNT.fetch_add(1, Ordering::AcqRel) != 0 {
 		return;
 	}
 
 	match KinectState::new() {
 		Ok(kinect) => unsafe {
-			KINECT = Some(kinect);
+			*state() = Some(kinect);
 
 			lua.get_global(lua_string!("hook"));
 			lua.get_field(-1, lua_string!("Add"));
@@ -69,18 +316,25 @@ pub unsafe fn init(lua: gmod::lua::State) {
 }
 
 pub unsafe fn shutdown() {
-	INIT_REFCOUNT = INIT_REFCOUNT.saturating_sub(1);
-
-	if INIT_REFCOUNT == 0 {
-		KINECT = None;
+	if INIT_REFCOUNT
+		.fetch_update(Ordering::AcqRel, Ordering::Acquire, |refcount| Some(refcount.saturating_sub(1)))
+		.is_ok_and(|refcount| refcount <= 1)
+	{
+		// Take it out first so the polling thread isn't joined while the lock is held
+		let kinect = state().take();
+		drop(kinect);
 	}
 }
 
 pub unsafe fn already_initialized() -> bool {
-	INIT_REFCOUNT != 0
+	INIT_REFCOUNT.load(Ordering::Acquire) != 0
 }
 
+/// Only ever locked from the Lua thread, so this won't block for long.
+///
+/// Never call into Lua while holding it: a Lua error unwinds past the guard without unlocking it, and the next call would deadlock.
+/// Copy out what's needed first.
 #[inline]
-pub unsafe fn state() -> Option<&'static mut KinectState> {
-	KINECT.as_mut()
+pub fn state() -> MutexGuard<'static, Option<KinectState>> {
+	KINECT.lock().unwrap_or_else(PoisonError::into_inner)
 }
@@ -15,7 +15,9 @@ WinSdkKinectV1::WinSdkKinectV1(
 													   m_pNuiSensor(NULL),
 													   m_Callback(callback),
 													   m_pCallbackUserData(userdata),
-													   m_bAvailable(false)
+													   m_State(WINSDK_KINECT_NOT_CONNECTED),
+													   m_Sequence(0),
+													   m_Timestamp(0)
 {
 	for (int i = 0; i < NUI_SKELETON_COUNT; ++i)
 	{
@@ -92,7 +94,7 @@ void CALLBACK WinSdkKinectV1::DeviceStatusChanged(HRESULT hrStatus, const OLECHA
 
 	if (kinect->m_pNuiSensor)
 	{
-		kinect->m_bAvailable.store(false, std::memory_order_release);
+		kinect->m_State.store(WINSDK_KINECT_NOT_CONNECTED, std::memory_order_release);
 
 		kinect->m_pNuiSensor->NuiShutdown();
 		kinect->m_pNuiSensor->Release();
@@ -106,13 +108,15 @@ void CALLBACK WinSdkKinectV1::DeviceStatusChanged(HRESULT hrStatus, const OLECHA
 
 			if (trackingStateChanged)
 			{
-				kinect->m_Callback({(uintptr_t)i, NULL}, kinect->m_pCallbackUserData);
+				kinect->m_Callback({(uintptr_t)i, 0, kinect->m_Sequence, kinect->m_Timestamp, NULL, NULL, NULL}, kinect->m_pCallbackUserData);
 			}
 		}
 	}
 
 	if (SUCCEEDED(hrStatus))
 	{
+		kinect->m_State.store(WINSDK_KINECT_INITIALIZING, std::memory_order_release);
+
 		INuiSensor *pNuiSensor;
 		HRESULT hr = NuiCreateSensorById(instanceName, &pNuiSensor);
 
@@ -136,7 +140,7 @@ void CALLBACK WinSdkKinectV1::DeviceStatusChanged(HRESULT hrStatus, const OLECHA
 			if (SUCCEEDED(hr))
 			{
 				kinect->m_pNuiSensor = pNuiSensor;
-				kinect->m_bAvailable.store(true, std::memory_order_release);
+				kinect->m_State.store(WINSDK_KINECT_READY, std::memory_order_release);
 			}
 			else
 			{
@@ -144,6 +148,11 @@ void CALLBACK WinSdkKinectV1::DeviceStatusChanged(HRESULT hrStatus, const OLECHA
 				pNuiSensor->Release();
 			}
 		}
+
+		if (!kinect->m_pNuiSensor)
+		{
+			kinect->m_State.store(WINSDK_KINECT_NOT_CONNECTED, std::memory_order_release);
+		}
 	}
 }
 
@@ -187,6 +196,8 @@ HRESULT WinSdkKinectV1::MonitorSensors()
 
 	if (NULL != m_pNuiSensor)
 	{
+		m_State.store(WINSDK_KINECT_INITIALIZING, std::memory_order_release);
+
 		// Initialize the Kinect and specify that we'll be using skeleton
 		hr = m_pNuiSensor->NuiInitialize(NUI_INITIALIZE_FLAG_USES_SKELETON);
 
@@ -198,7 +209,11 @@ HRESULT WinSdkKinectV1::MonitorSensors()
 
 		if (SUCCEEDED(hr))
 		{
-			m_bAvailable.store(true, std::memory_order_release);
+			m_State.store(WINSDK_KINECT_READY, std::memory_order_release);
+		}
+		else
+		{
+			m_State.store(WINSDK_KINECT_NOT_CONNECTED, std::memory_order_release);
 		}
 	}
 
@@ -221,6 +236,9 @@ void WinSdkKinectV1::ProcessSkeleton()
 		return;
 	}
 
+	m_Sequence++;
+	m_Timestamp = (uint64_t)skeletonFrame.liTimeStamp.QuadPart * 1000;
+
 	// smooth out the skeleton data
 	m_pNuiSensor->NuiTransformSmooth(&skeletonFrame, NULL);
 
@@ -234,11 +252,26 @@ void WinSdkKinectV1::ProcessSkeleton()
 
 		if (bIsTracked)
 		{
-			m_Callback({(uintptr_t)i, skeletonFrame.SkeletonData[i].SkeletonPositions}, m_pCallbackUserData);
+			NUI_SKELETON_BONE_ORIENTATION boneOrientations[NUI_SKELETON_POSITION_COUNT];
+			Vector4 orientations[NUI_SKELETON_POSITION_COUNT];
+			Vector4 *pOrientations = NULL;
+
+			if (SUCCEEDED(NuiSkeletonCalculateBoneOrientations(&skeletonFrame.SkeletonData[i], boneOrientations)))
+			{
+				// Bone orientations are keyed by the joint at the end of the bone
+				for (int j = 0; j < NUI_SKELETON_POSITION_COUNT; ++j)
+				{
+					orientations[boneOrientations[j].endJoint] = boneOrientations[j].absoluteRotation.rotationQuaternion;
+				}
+
+				pOrientations = orientations;
+			}
+
+			m_Callback({(uintptr_t)i, skeletonFrame.SkeletonData[i].dwTrackingID, m_Sequence, m_Timestamp, skeletonFrame.SkeletonData[i].SkeletonPositions, skeletonFrame.SkeletonData[i].eSkeletonPositionTrackingState, pOrientations, skeletonFrame.vFloorClipPlane, skeletonFrame.vNormalToGravity}, m_pCallbackUserData);
 		}
 		else if (trackingStateChanged)
 		{
-			m_Callback({(uintptr_t)i, NULL}, m_pCallbackUserData);
+			m_Callback({(uintptr_t)i, 0, m_Sequence, m_Timestamp, NULL, NULL, NULL, skeletonFrame.vFloorClipPlane, skeletonFrame.vNormalToGravity}, m_pCallbackUserData);
 		}
 	}
 }
\ No newline at end of file
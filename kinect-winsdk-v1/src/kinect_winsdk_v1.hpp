@@ -8,12 +8,28 @@ extern "C"
 	struct WinSdkKinectV1SkeletonUpdate
 	{
 		uintptr_t skeletonIndex;
+		uint64_t trackingId;
+		uint64_t sequence;
+		uint64_t timestamp; // Microseconds
 		Vector4 *bones;
+		NUI_SKELETON_POSITION_TRACKING_STATE *trackingStates;
+		Vector4 *orientations;
+		Vector4 floorClipPlane; // Zero if the floor wasn't found
+		Vector4 normalToGravity; // Zero if unknown
 	};
 
 	typedef void (*WinSdkKinectV1Callback)(WinSdkKinectV1SkeletonUpdate, void *);
 }
 
+// Mirrors kinect::KinectBackendState
+enum WinSdkKinectState : uint32_t
+{
+	WINSDK_KINECT_NOT_CONNECTED = 0,
+	WINSDK_KINECT_INITIALIZING = 1,
+	WINSDK_KINECT_READY = 2,
+	WINSDK_KINECT_LOST = 3,
+};
+
 class WinSdkKinectV1
 {
 public:
@@ -25,7 +41,7 @@ public:
 	HRESULT MonitorSensors();
 
 	void *m_pCallbackUserData;
-	std::atomic<bool> m_bAvailable;
+	std::atomic<uint32_t> m_State;
 
 private:
 	void Update(DWORD event);
@@ -37,6 +53,11 @@ private:
 
 	WinSdkKinectV1Callback m_Callback;
 
+	// Incremented for every skeleton frame
+	uint64_t m_Sequence;
+	// Timestamp of the last skeleton frame, in microseconds
+	uint64_t m_Timestamp;
+
 	// Current Kinect
 	INuiSensor *m_pNuiSensor;
 	HANDLE m_hNextSkeletonEvent;
@@ -73,7 +94,7 @@ extern "C" HRESULT WinSdkKinectV1_Run(WinSdkKinectV1 *pKinect)
 	return pKinect->Run();
 }
 
-extern "C" bool WinSdkKinectV1_Available(WinSdkKinectV1 *pKinect)
+extern "C" uint32_t WinSdkKinectV1_State(WinSdkKinectV1 *pKinect)
 {
-	return pKinect->m_bAvailable.load(std::memory_order_acquire);
+	return pKinect->m_State.load(std::memory_order_acquire);
 }
\ No newline at end of file
@@ -1,7 +1,19 @@
 #![cfg(windows)]
 
-use kinect::{KinectBackend, KinectSkeleton, KinectSkeletonRawBones, KinectTrackedSkeleton};
-use std::{ffi::c_void, marker::PhantomData, mem::ManuallyDrop, os::windows::io::AsRawHandle};
+use kinect::{
+	abi::KinectBackendWaker, KinectBackend, KinectBackendState, KinectBody, KinectBodyId, KinectFloorPlane, KinectFrame, KinectRetargetTable,
+	KinectSensorOrientation, KinectSkeleton, KinectTrackingState, Mailbox,
+};
+use std::{
+	ffi::c_void,
+	mem::ManuallyDrop,
+	os::windows::io::AsRawHandle,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
 use windows::{
 	core::HRESULT,
 	Win32::{
@@ -12,10 +24,12 @@ use windows::{
 };
 
 const BONE_COUNT: usize = 20;
+const SKELETON_COUNT: usize = 6;
 
 #[inline]
-fn convert_kinect_coordinate_space_to_gmod(vector: &Vector4) -> [f32; 3] {
-	[-vector.x, vector.z, vector.y]
+fn convert_kinect_tracking_state(state: i32) -> KinectTrackingState {
+	// NUI_SKELETON_POSITION_TRACKING_STATE matches ours
+	u8::try_from(state).map_or(KinectTrackingState::NotTracked, KinectTrackingState::from_raw)
 }
 
 #[link(name = "kinect_winsdk_v1_cpp", kind = "static")]
@@ -23,7 +37,7 @@ extern "C" {
 	fn WinSdkKinectV1_Create(callback: CWinSdkKinectV1Callback, userdata: *mut c_void, result: &mut HRESULT) -> *mut c_void;
 	fn WinSdkKinectV1_Destroy(ptr: *mut c_void);
 	fn WinSdkKinectV1_Run(ptr: *mut c_void) -> HRESULT;
-	fn WinSdkKinectV1_Available(ptr: *mut c_void) -> bool;
+	fn WinSdkKinectV1_State(ptr: *mut c_void) -> u32;
 }
 
 type CWinSdkKinectV1Callback = extern "C" fn(WinSdkKinectV1SkeletonUpdate, *mut c_void);
@@ -36,7 +50,14 @@ unsafe impl<T> Sync for SendPtr<T> {}
 #[repr(C)]
 struct WinSdkKinectV1SkeletonUpdate {
 	skeleton_index: usize,
+	tracking_id: u64,
+	sequence: u64,
+	timestamp: u64,
 	skeleton: *const WinSdkKinectV1Skeleton,
+	tracking_states: *const [i32; BONE_COUNT],
+	orientations: *const [Vector4; BONE_COUNT],
+	floor_clip_plane: Vector4,
+	normal_to_gravity: Vector4,
 }
 impl WinSdkKinectV1SkeletonUpdate {
 	#[inline]
@@ -47,12 +68,68 @@ impl WinSdkKinectV1SkeletonUpdate {
 			None
 		}
 	}
+
+	#[inline]
+	fn tracking_states(&self) -> Option<&[i32; BONE_COUNT]> {
+		if !self.tracking_states.is_null() {
+			Some(unsafe { &*self.tracking_states })
+		} else {
+			None
+		}
+	}
+
+	#[inline]
+	fn orientations(&self) -> Option<&[Vector4; BONE_COUNT]> {
+		if !self.orientations.is_null() {
+			Some(unsafe { &*self.orientations })
+		} else {
+			None
+		}
+	}
+
+	#[inline]
+	fn ground(&self) -> (Option<KinectFloorPlane>, Option<KinectSensorOrientation>) {
+		(
+			KinectFloorPlane::new(self.floor_clip_plane.xyz(), self.floor_clip_plane.w),
+			KinectSensorOrientation::new(self.normal_to_gravity.xyz()),
+		)
+	}
+
+	/// Copies the update out of SDK-owned memory, so this must be called before the callback returns.
+	fn kinect_body(&self) -> KinectBody {
+		KinectBody {
+			id: KinectBodyId(self.tracking_id),
+			frame: self.kinect_frame(self.kinect_skeleton().unwrap_or_default()),
+		}
+	}
+
+	#[inline]
+	fn kinect_frame(&self, skeleton: KinectSkeleton) -> KinectFrame {
+		KinectFrame {
+			skeleton,
+			timestamp: Duration::from_micros(self.timestamp),
+			sequence: self.sequence,
+		}
+	}
+
+	fn kinect_skeleton(&self) -> Option<KinectSkeleton> {
+		let bones = self.skeleton()?.raw_bones().map(|bone| bone.xyz());
+		let tracking_states = self.tracking_states()?.map(convert_kinect_tracking_state);
+		let orientations = self.orientations().map(|orientations| orientations.map(|orientation| orientation.xyzw()));
+
+		KinectRetargetTable::KINECT_V1.retarget(&bones, &tracking_states, orientations.as_ref().map(|orientations| &orientations[..]))
+	}
 }
 impl std::fmt::Debug for WinSdkKinectV1SkeletonUpdate {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("WinSdkKinectV1SkeletonUpdate")
 			.field("skeleton_index", &self.skeleton_index)
+			.field("tracking_id", &self.tracking_id)
+			.field("sequence", &self.sequence)
+			.field("timestamp", &self.timestamp)
 			.field("skeleton", &self.skeleton())
+			.field("tracking_states", &self.tracking_states())
+			.field("orientations", &self.orientations())
 			.finish()
 	}
 }
@@ -65,6 +142,17 @@ struct Vector4 {
 	z: f32,
 	w: f32,
 }
+impl Vector4 {
+	#[inline]
+	fn xyz(&self) -> [f32; 3] {
+		[self.x, self.y, self.z]
+	}
+
+	#[inline]
+	fn xyzw(&self) -> [f32; 4] {
+		[self.x, self.y, self.z, self.w]
+	}
+}
 
 #[derive(Clone, Copy)]
 #[repr(C)]
@@ -116,28 +204,33 @@ struct NamedSensorBones {
 
 struct WinSdkKinectV1<U> {
 	ptr: *mut c_void,
+	userdata: *mut U,
 	thread: ManuallyDrop<std::thread::JoinHandle<()>>,
-	_userdata: PhantomData<U>,
+	/// Set if the thread exited by itself
+	lost: Arc<AtomicBool>,
 }
 impl<U> WinSdkKinectV1<U> {
 	#[inline]
 	fn new(callback: WinSdkKinectV1Callback<U>, userdata: U) -> Result<Self, std::io::Error> {
 		Self::new_(
 			unsafe { core::mem::transmute::<_, CWinSdkKinectV1Callback>(callback) },
-			Box::into_raw(Box::new(userdata)) as *mut c_void,
+			Box::into_raw(Box::new(userdata)),
 		)
 	}
 
-	fn new_(callback: CWinSdkKinectV1Callback, userdata: *mut c_void) -> Result<Self, std::io::Error> {
+	fn new_(callback: CWinSdkKinectV1Callback, userdata: *mut U) -> Result<Self, std::io::Error> {
 		let mut res = HRESULT(0);
-		let ptr = unsafe { WinSdkKinectV1_Create(callback, userdata, &mut res) };
+		let ptr = unsafe { WinSdkKinectV1_Create(callback, userdata as *mut c_void, &mut res) };
 		if !ptr.is_null() && res.is_ok() {
+			let lost = Arc::new(AtomicBool::new(false));
+
 			Ok(Self {
 				ptr,
+				userdata,
 
 				thread: ManuallyDrop::new({
 					let ptr = SendPtr(ptr);
-					let userdata = SendPtr(userdata);
+					let lost = lost.clone();
 					std::thread::Builder::new()
 						.name("rekinect_winsdk_v1".to_string())
 						.spawn(move || unsafe {
@@ -145,21 +238,24 @@ impl<U> WinSdkKinectV1<U> {
 							let ptr = ptr.0;
 							if let Err(err) = WinSdkKinectV1_Run(ptr).ok() {
 								log::error!("WinSdkKinectV1_Run() failed ({:?})", err);
+								lost.store(true, Ordering::Release);
 							}
-							WinSdkKinectV1_Destroy(ptr);
-
-							let userdata = { userdata };
-							let userdata = userdata.0;
-							drop(Box::from_raw(userdata as *mut U));
 
 							log::info!("WinSdkKinectV1 thread exited");
 						})
 						.unwrap()
 				}),
 
-				_userdata: PhantomData,
+				lost,
 			})
 		} else {
+			unsafe {
+				if !ptr.is_null() {
+					WinSdkKinectV1_Destroy(ptr);
+				}
+				drop(Box::from_raw(userdata));
+			}
+
 			Err(std::io::Error::new(
 				std::io::ErrorKind::Other,
 				format!("WinSdkKinectV1_Create() failed ({res:?})"),
@@ -174,57 +270,116 @@ impl<U> Drop for WinSdkKinectV1<U> {
 			PostThreadMessageW(GetThreadId(HANDLE(thread.as_raw_handle() as isize)), WM_QUIT, WPARAM(0), LPARAM(0)).ok();
 		}
 		thread.join().ok();
+
+		// The thread has exited, so nothing else can be using these anymore
+		unsafe {
+			WinSdkKinectV1_Destroy(self.ptr);
+			drop(Box::from_raw(self.userdata));
+		}
 	}
 }
 
-#[no_mangle]
-pub extern "Rust" fn gmcl_rekinect_init(logger: &'static dyn log::Log) -> Result<Box<dyn KinectBackend>, std::io::Error> {
-	log::set_logger(logger).ok();
-	log::set_max_level(log::LevelFilter::Info);
+kinect::export_backend! {
+	name: "rekinect_winsdk_v1",
+	capabilities: [ORIENTATIONS, TRACKING_STATES, MULTIPLE_BODIES, SENSOR_TIMESTAMPS, FLOOR_PLANE, SENSOR_ORIENTATION],
+	init: init,
+}
+
+fn init(waker: KinectBackendWaker) -> Result<Box<dyn KinectBackend>, std::io::Error> {
+	type Ground = (Option<KinectFloorPlane>, Option<KinectSensorOrientation>);
 
-	extern "C" fn callback(event: WinSdkKinectV1SkeletonUpdate, tx: &mut std::sync::mpsc::SyncSender<WinSdkKinectV1SkeletonUpdate>) {
-		tx.send(event).ok();
+	#[derive(Default)]
+	struct Mailboxes {
+		bodies: [Mailbox<KinectBody>; SKELETON_COUNT],
+		ground: Mailbox<Ground>,
+	}
+
+	extern "C" fn callback(event: WinSdkKinectV1SkeletonUpdate, (mailboxes, waker): &mut (Arc<Mailboxes>, KinectBackendWaker)) {
+		// The event points into memory owned by the SDK, so copy it out before handing it over to whoever is polling
+		mailboxes.ground.post(event.ground());
+
+		if let Some(mailbox) = mailboxes.bodies.get(event.skeleton_index) {
+			mailbox.post(event.kinect_body());
+			waker.wake();
+		}
 	}
 
-	let (tx, rx) = std::sync::mpsc::sync_channel(1);
-	let kinect = WinSdkKinectV1::new(callback, tx)?;
+	let mailboxes = Arc::new(Mailboxes::default());
+	let kinect = WinSdkKinectV1::new(callback, (mailboxes.clone(), waker))?;
 
 	struct WinSdkKinectBackend {
-		rx: std::sync::mpsc::Receiver<WinSdkKinectV1SkeletonUpdate>,
-		skeleton: Option<usize>,
-		inner: WinSdkKinectV1<std::sync::mpsc::SyncSender<WinSdkKinectV1SkeletonUpdate>>,
+		mailboxes: Arc<Mailboxes>,
+		bodies: [Option<KinectBodyId>; SKELETON_COUNT],
+		pending: Option<KinectBody>,
+		ground: Ground,
+		inner: WinSdkKinectV1<(Arc<Mailboxes>, KinectBackendWaker)>,
 	}
 	impl KinectBackend for WinSdkKinectBackend {
-		fn poll(&mut self) -> Option<KinectSkeleton> {
-			let event = self.rx.try_recv().ok()?;
-			if self.skeleton.is_none() || self.skeleton == Some(event.skeleton_index) {
-				if let Some(skeleton) = event.skeleton() {
-					self.skeleton = Some(event.skeleton_index);
-
-					let mut raw_bones = KinectSkeletonRawBones::default();
-
-					skeleton.raw_bones().iter().zip(raw_bones.iter_mut()).for_each(|(src, dst)| {
-						*dst = convert_kinect_coordinate_space_to_gmod(src);
-					});
-
-					return Some(KinectSkeleton::Tracked(KinectTrackedSkeleton::from_raw_bones(raw_bones)));
-				} else if self.skeleton.is_some() {
-					self.skeleton = None;
-					return Some(KinectSkeleton::Untracked);
+		fn poll(&mut self) -> Option<KinectBody> {
+			if let Some(ground) = self.mailboxes.ground.take() {
+				self.ground = ground;
+			}
+
+			if let Some(body) = self.pending.take() {
+				return Some(body);
+			}
+
+			for (slot, mailbox) in self.bodies.iter_mut().zip(self.mailboxes.bodies.iter()) {
+				let Some(body) = mailbox.take() else {
+					continue;
+				};
+
+				if let KinectSkeleton::Untracked = body.frame.skeleton {
+					// This body is no longer being tracked
+					if let Some(id) = slot.take() {
+						return Some(KinectBody { id, frame: body.frame });
+					}
+					continue;
+				}
+
+				match slot.replace(body.id) {
+					// The sensor reused this slot for someone else without telling us the previous body was lost
+					Some(id) if id != body.id => {
+						let frame = KinectFrame {
+							skeleton: KinectSkeleton::Untracked,
+							..body.frame
+						};
+						self.pending = Some(body);
+						return Some(KinectBody { id, frame });
+					}
+
+					_ => return Some(body),
 				}
 			}
+
 			None
 		}
 
 		#[inline]
-		fn available(&self) -> bool {
-			unsafe { WinSdkKinectV1_Available(self.inner.ptr) }
+		fn state(&self) -> KinectBackendState {
+			if self.inner.lost.load(Ordering::Acquire) {
+				KinectBackendState::Lost
+			} else {
+				KinectBackendState::from_raw(unsafe { WinSdkKinectV1_State(self.inner.ptr) })
+			}
+		}
+
+		#[inline]
+		fn floor_plane(&self) -> Option<KinectFloorPlane> {
+			self.ground.0
+		}
+
+		#[inline]
+		fn sensor_orientation(&self) -> Option<KinectSensorOrientation> {
+			self.ground.1
 		}
 	}
 
 	Ok(Box::new(WinSdkKinectBackend {
-		rx,
+		mailboxes,
 		inner: kinect,
-		skeleton: None,
+		bodies: [None; SKELETON_COUNT],
+		pending: None,
+		ground: Ground::default(),
 	}))
 }
@@ -20,7 +20,10 @@ WinSdkKinectV2::WinSdkKinectV2(WinSdkKinectV2Callback callback, void *userdata)
 																				  m_pCallbackUserData(userdata),
 																				  m_AvailablityChangedEvent(INVALID_WAITABLE_HANDLE),
 																				  m_BodyFrameArrivedEvent(INVALID_WAITABLE_HANDLE),
-																				  m_bAvailable(false)
+																				  m_State(WINSDK_KINECT_NOT_CONNECTED),
+																				  m_Sequence(0),
+																				  m_Timestamp(0),
+																				  m_FloorClipPlane({0, 0, 0, 0})
 {
 	for (int i = 0; i < BODY_COUNT; ++i)
 	{
@@ -100,7 +103,7 @@ void WinSdkKinectV2::BodyFrameArrived()
 	{
 		// I don't understand how they managed to mess this part of the SDK up so badly, but this seems to be a hacky fix for this event just not working properly...
 		BOOLEAN bAvailable = FALSE;
-		if (SUCCEEDED(m_pKinectSensor->get_IsAvailable(&bAvailable)) && m_bAvailable.load(std::memory_order_relaxed) != !!bAvailable)
+		if (SUCCEEDED(m_pKinectSensor->get_IsAvailable(&bAvailable)) && (m_State.load(std::memory_order_relaxed) == WINSDK_KINECT_READY) != !!bAvailable)
 		{
 			AvailableChanged();
 		}
@@ -117,6 +120,20 @@ void WinSdkKinectV2::BodyFrameArrived()
 
 	if (SUCCEEDED(hr) && pBodyFrame)
 	{
+		TIMESPAN relativeTime = 0;
+		m_Sequence++;
+
+		// RelativeTime is in 100ns ticks
+		if (SUCCEEDED(pBodyFrame->get_RelativeTime(&relativeTime)))
+		{
+			m_Timestamp = (uint64_t)relativeTime / 10;
+		}
+
+		if (FAILED(pBodyFrame->get_FloorClipPlane(&m_FloorClipPlane)))
+		{
+			m_FloorClipPlane = {0, 0, 0, 0};
+		}
+
 		IBody *ppBodies[BODY_COUNT] = {0};
 
 		hr = pBodyFrame->GetAndRefreshBodyData(_countof(ppBodies), ppBodies);
@@ -153,7 +170,7 @@ void WinSdkKinectV2::AvailableChanged()
 
 		if (SUCCEEDED(hr))
 		{
-			m_bAvailable.store(bAvailable, std::memory_order_release);
+			m_State.store(bAvailable ? WINSDK_KINECT_READY : WINSDK_KINECT_NOT_CONNECTED, std::memory_order_release);
 
 			if (!bAvailable)
 			{
@@ -165,7 +182,7 @@ void WinSdkKinectV2::AvailableChanged()
 
 					if (trackingStateChanged)
 					{
-						m_Callback({(uintptr_t)i, NULL}, m_pCallbackUserData);
+						m_Callback({(uintptr_t)i, 0, m_Sequence, m_Timestamp, NULL, NULL, NULL}, m_pCallbackUserData);
 					}
 				}
 			}
@@ -213,6 +230,12 @@ HRESULT WinSdkKinectV2::InitializeDefaultSensor()
 			hr = m_pKinectSensor->Open();
 		}
 
+		if (SUCCEEDED(hr))
+		{
+			// The sensor reports whether it's actually available shortly after being opened
+			m_State.store(WINSDK_KINECT_INITIALIZING, std::memory_order_release);
+		}
+
 		if (SUCCEEDED(hr))
 		{
 			hr = m_pKinectSensor->get_CoordinateMapper(&m_pCoordinateMapper);
@@ -266,24 +289,51 @@ void WinSdkKinectV2::ProcessBody(IBody **ppBodies)
 				{
 					if (trackingStateChanged)
 					{
-						m_Callback({(uintptr_t)i, NULL}, m_pCallbackUserData);
+						m_Callback({(uintptr_t)i, 0, m_Sequence, m_Timestamp, NULL, NULL, NULL, {}, {}, m_FloorClipPlane}, m_pCallbackUserData);
 					}
 				}
 				else
 				{
+					UINT64 trackingId = 0;
+					pBody->get_TrackingId(&trackingId);
+
 					Joint joints[JointType_Count];
 					hr = pBody->GetJoints(_countof(joints), joints);
 
 					if (SUCCEEDED(hr))
 					{
 						CameraSpacePoint positions[JointType_Count];
+						TrackingState trackingStates[JointType_Count];
 
 						for (int j = 0; j < _countof(joints); ++j)
 						{
 							positions[j] = joints[j].Position;
+							trackingStates[j] = joints[j].TrackingState;
 						}
 
-						m_Callback({(uintptr_t)i, positions}, m_pCallbackUserData);
+						JointOrientation jointOrientations[JointType_Count];
+						Vector4 orientations[JointType_Count];
+						Vector4 *pOrientations = NULL;
+
+						if (SUCCEEDED(pBody->GetJointOrientations(_countof(jointOrientations), jointOrientations)))
+						{
+							for (int j = 0; j < _countof(jointOrientations); ++j)
+							{
+								orientations[jointOrientations[j].JointType] = jointOrientations[j].Orientation;
+							}
+
+							pOrientations = orientations;
+						}
+
+						HandState handStates[2] = {HandState_Unknown, HandState_Unknown};
+						TrackingConfidence handConfidences[2] = {TrackingConfidence_Low, TrackingConfidence_Low};
+
+						pBody->get_HandLeftState(&handStates[0]);
+						pBody->get_HandLeftConfidence(&handConfidences[0]);
+						pBody->get_HandRightState(&handStates[1]);
+						pBody->get_HandRightConfidence(&handConfidences[1]);
+
+						m_Callback({(uintptr_t)i, trackingId, m_Sequence, m_Timestamp, positions, trackingStates, pOrientations, {handStates[0], handStates[1]}, {handConfidences[0], handConfidences[1]}, m_FloorClipPlane}, m_pCallbackUserData);
 					}
 				}
 			}
@@ -8,12 +8,29 @@ extern "C"
 	struct WinSdkKinectV2SkeletonUpdate
 	{
 		uintptr_t skeletonIndex;
+		uint64_t trackingId;
+		uint64_t sequence;
+		uint64_t timestamp; // Microseconds
 		CameraSpacePoint *skeleton;
+		TrackingState *trackingStates;
+		Vector4 *orientations;
+		HandState handStates[2]; // Left, right
+		TrackingConfidence handConfidences[2]; // Left, right
+		Vector4 floorClipPlane; // Zero if the floor wasn't found
 	};
 
 	typedef void (*WinSdkKinectV2Callback)(WinSdkKinectV2SkeletonUpdate, void *);
 }
 
+// Mirrors kinect::KinectBackendState
+enum WinSdkKinectState : uint32_t
+{
+	WINSDK_KINECT_NOT_CONNECTED = 0,
+	WINSDK_KINECT_INITIALIZING = 1,
+	WINSDK_KINECT_READY = 2,
+	WINSDK_KINECT_LOST = 3,
+};
+
 class WinSdkKinectV2
 {
 public:
@@ -26,7 +43,7 @@ public:
 	HRESULT Run();
 
 	void *m_pCallbackUserData;
-	std::atomic<bool> m_bAvailable;
+	std::atomic<uint32_t> m_State;
 
 private:
 	// Current Kinect
@@ -40,6 +57,13 @@ private:
 
 	WinSdkKinectV2Callback m_Callback;
 
+	// Incremented for every skeleton frame
+	uint64_t m_Sequence;
+	// Timestamp of the last skeleton frame, in microseconds
+	uint64_t m_Timestamp;
+	// Floor of the last skeleton frame
+	Vector4 m_FloorClipPlane;
+
 	BOOLEAN m_SkeletonTrackingStates[BODY_COUNT];
 
 	/// Main processing function
@@ -61,6 +85,7 @@ extern "C" WinSdkKinectV2 *WinSdkKinectV2_Create(WinSdkKinectV2Callback callback
 
 	if (FAILED(*result))
 	{
+		delete pKinect;
 		return NULL;
 	}
 	else
@@ -83,7 +108,7 @@ extern "C" HRESULT WinSdkKinectV2_Run(WinSdkKinectV2 *pKinect)
 	return pKinect->Run();
 }
 
-extern "C" bool WinSdkKinectV2_Available(WinSdkKinectV2 *pKinect)
+extern "C" uint32_t WinSdkKinectV2_State(WinSdkKinectV2 *pKinect)
 {
-	return pKinect->m_bAvailable.load(std::memory_order_acquire);
+	return pKinect->m_State.load(std::memory_order_acquire);
 }
\ No newline at end of file
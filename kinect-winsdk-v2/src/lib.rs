@@ -1,12 +1,18 @@
 #![cfg(windows)]
 
-use kinect::{KinectBackend, KinectExtendedSkeletonBones, KinectSkeleton, KinectSkeletonBones, KinectTrackedExtendedSkeleton, KinectTrackedSkeleton};
+use kinect::{
+	abi::KinectBackendWaker, KinectBackend, KinectBackendState, KinectBody, KinectBodyId, KinectFloorPlane, KinectFrame, KinectHand,
+	KinectHandConfidence, KinectHandState, KinectHands, KinectRetargetTable, KinectSensorOrientation, KinectSkeleton, KinectTrackingState, Mailbox,
+};
 use std::{
 	ffi::c_void,
-	marker::PhantomData,
 	mem::ManuallyDrop,
-	ops::{Add, Div},
 	os::windows::io::AsRawHandle,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	time::Duration,
 };
 use windows::{
 	core::HRESULT,
@@ -18,18 +24,14 @@ use windows::{
 };
 
 const BONE_COUNT: usize = 25;
-
-#[inline]
-fn convert_kinect_coordinate_space_to_gmod(vector: Vector3) -> [f32; 3] {
-	[-vector.x, vector.z, vector.y]
-}
+const BODY_COUNT: usize = 6;
 
 #[link(name = "kinect_winsdk_v2_cpp", kind = "static")]
 extern "C" {
 	fn WinSdkKinectV2_Create(callback: CWinSdkKinectV2Callback, userdata: *mut c_void, res: &mut HRESULT) -> *mut c_void;
 	fn WinSdkKinectV2_Destroy(ptr: *mut c_void);
 	fn WinSdkKinectV2_Run(ptr: *mut c_void) -> HRESULT;
-	fn WinSdkKinectV2_Available(ptr: *mut c_void) -> bool;
+	fn WinSdkKinectV2_State(ptr: *mut c_void) -> u32;
 }
 
 type CWinSdkKinectV2Callback = extern "C" fn(WinSdkKinectV2SkeletonUpdate, *mut c_void);
@@ -42,23 +44,110 @@ unsafe impl<T> Sync for SendPtr<T> {}
 #[repr(C)]
 struct WinSdkKinectV2SkeletonUpdate {
 	skeleton_index: usize,
-	skeleton: *const SensorBones,
+	tracking_id: u64,
+	sequence: u64,
+	timestamp: u64,
+	skeleton: *const SensorBones<Vector3>,
+	tracking_states: *const SensorBones<TrackingState>,
+	orientations: *const SensorBones<Vector4>,
+	/// `[left, right]` HandState
+	hand_states: [i32; 2],
+	/// `[left, right]` TrackingConfidence
+	hand_confidences: [i32; 2],
+	floor_clip_plane: Vector4,
 }
 impl WinSdkKinectV2SkeletonUpdate {
 	#[inline]
-	fn skeleton(&self) -> Option<&SensorBones> {
+	fn skeleton(&self) -> Option<&SensorBones<Vector3>> {
 		if !self.skeleton.is_null() {
 			Some(unsafe { &*self.skeleton })
 		} else {
 			None
 		}
 	}
+
+	#[inline]
+	fn tracking_states(&self) -> Option<&SensorBones<TrackingState>> {
+		if !self.tracking_states.is_null() {
+			Some(unsafe { &*self.tracking_states })
+		} else {
+			None
+		}
+	}
+
+	#[inline]
+	fn orientations(&self) -> Option<&SensorBones<Vector4>> {
+		if !self.orientations.is_null() {
+			Some(unsafe { &*self.orientations })
+		} else {
+			None
+		}
+	}
+
+	#[inline]
+	fn hands(&self) -> KinectHands {
+		// HandState and TrackingConfidence line up with KinectHandState and KinectHandConfidence
+		let hand = |i: usize| KinectHand {
+			state: KinectHandState::from_raw(u8::try_from(self.hand_states[i]).unwrap_or_default()),
+			confidence: KinectHandConfidence::from_raw(u8::try_from(self.hand_confidences[i]).unwrap_or_default()),
+		};
+
+		KinectHands {
+			left: hand(0),
+			right: hand(1),
+		}
+	}
+
+	/// The SDK only tells us the floor, so up is the floor's normal.
+	#[inline]
+	fn ground(&self) -> (Option<KinectFloorPlane>, Option<KinectSensorOrientation>) {
+		let [x, y, z, w] = self.floor_clip_plane.into_array();
+		let floor = KinectFloorPlane::new([x, y, z], w);
+
+		(floor, floor.map(KinectSensorOrientation::from))
+	}
+
+	/// Copies the update out of SDK-owned memory, so this must be called before the callback returns.
+	fn kinect_body(&self) -> KinectBody {
+		KinectBody {
+			id: KinectBodyId(self.tracking_id),
+			frame: self.kinect_frame(self.kinect_skeleton().unwrap_or_default()),
+		}
+	}
+
+	#[inline]
+	fn kinect_frame(&self, skeleton: KinectSkeleton) -> KinectFrame {
+		KinectFrame {
+			skeleton,
+			timestamp: Duration::from_micros(self.timestamp),
+			sequence: self.sequence,
+		}
+	}
+
+	fn kinect_skeleton(&self) -> Option<KinectSkeleton> {
+		let bones = self.skeleton()?.raw().map(Vector3::into_array);
+		let tracking_states = self.tracking_states()?.raw().map(TrackingState::into_kinect);
+		let orientations = self.orientations().map(|orientations| orientations.raw().map(Vector4::into_array));
+
+		let mut skeleton =
+			KinectRetargetTable::KINECT_V2.retarget(&bones, &tracking_states, orientations.as_ref().map(|orientations| &orientations[..]))?;
+		if let Some(tracked) = skeleton.tracked_mut() {
+			*tracked = tracked.with_hands(self.hands());
+		}
+
+		Some(skeleton)
+	}
 }
 impl std::fmt::Debug for WinSdkKinectV2SkeletonUpdate {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("WinSdkKinectV2SkeletonUpdate")
 			.field("skeleton_index", &self.skeleton_index)
+			.field("tracking_id", &self.tracking_id)
+			.field("sequence", &self.sequence)
+			.field("timestamp", &self.timestamp)
 			.field("skeleton", &self.skeleton())
+			.field("tracking_states", &self.tracking_states())
+			.field("orientations", &self.orientations())
 			.finish()
 	}
 }
@@ -72,41 +161,48 @@ struct Vector3 {
 }
 impl Vector3 {
 	#[inline]
-	fn into_gmod(self) -> [f32; 3] {
-		convert_kinect_coordinate_space_to_gmod(self)
+	fn into_array(self) -> [f32; 3] {
+		[self.x, self.y, self.z]
 	}
 }
-impl Add for Vector3 {
-	type Output = Self;
-
-	#[inline(always)]
-	fn add(self, rhs: Self) -> Self::Output {
-		Self {
-			x: self.x + rhs.x,
-			y: self.y + rhs.y,
-			z: self.z + rhs.z,
-		}
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct Vector4 {
+	x: f32,
+	y: f32,
+	z: f32,
+	w: f32,
+}
+impl Vector4 {
+	#[inline]
+	fn into_array(self) -> [f32; 4] {
+		[self.x, self.y, self.z, self.w]
 	}
 }
-impl Div<f32> for Vector3 {
-	type Output = Self;
 
-	#[inline(always)]
-	fn div(self, rhs: f32) -> Self::Output {
-		Self {
-			x: self.x / rhs,
-			y: self.y / rhs,
-			z: self.z / rhs,
-		}
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug)]
+struct TrackingState(i32);
+impl TrackingState {
+	#[inline]
+	fn into_kinect(self) -> KinectTrackingState {
+		// TrackingState_NotTracked, TrackingState_Inferred and TrackingState_Tracked match ours
+		u8::try_from(self.0).map_or(KinectTrackingState::NotTracked, KinectTrackingState::from_raw)
 	}
 }
 
 #[repr(C)]
-union SensorBones {
-	raw: [Vector3; BONE_COUNT],
-	named: NamedSensorBones,
+union SensorBones<T: Copy> {
+	raw: [T; BONE_COUNT],
+	named: NamedSensorBones<T>,
 }
-impl std::fmt::Debug for SensorBones {
+impl<T: Copy> SensorBones<T> {
+	#[inline(always)]
+	fn raw(&self) -> [T; BONE_COUNT] {
+		unsafe { self.raw }
+	}
+}
+impl<T: Copy + std::fmt::Debug> std::fmt::Debug for SensorBones<T> {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		unsafe { self.named.fmt(f) }
 	}
@@ -114,58 +210,63 @@ impl std::fmt::Debug for SensorBones {
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
-struct NamedSensorBones {
-	spine_base: Vector3,
-	spine_mid: Vector3,
-	neck: Vector3,
-	head: Vector3,
-	shoulder_left: Vector3,
-	elbow_left: Vector3,
-	wrist_left: Vector3,
-	hand_left: Vector3,
-	shoulder_right: Vector3,
-	elbow_right: Vector3,
-	wrist_right: Vector3,
-	hand_right: Vector3,
-	hip_left: Vector3,
-	knee_left: Vector3,
-	ankle_left: Vector3,
-	foot_left: Vector3,
-	hip_right: Vector3,
-	knee_right: Vector3,
-	ankle_right: Vector3,
-	foot_right: Vector3,
-	spine_shoulder: Vector3,
-	hand_tip_left: Vector3,
-	thumb_left: Vector3,
-	hand_tip_right: Vector3,
-	thumb_right: Vector3,
+struct NamedSensorBones<T> {
+	spine_base: T,
+	spine_mid: T,
+	neck: T,
+	head: T,
+	shoulder_left: T,
+	elbow_left: T,
+	wrist_left: T,
+	hand_left: T,
+	shoulder_right: T,
+	elbow_right: T,
+	wrist_right: T,
+	hand_right: T,
+	hip_left: T,
+	knee_left: T,
+	ankle_left: T,
+	foot_left: T,
+	hip_right: T,
+	knee_right: T,
+	ankle_right: T,
+	foot_right: T,
+	spine_shoulder: T,
+	hand_tip_left: T,
+	thumb_left: T,
+	hand_tip_right: T,
+	thumb_right: T,
 }
 
 struct WinSdkKinectV2<U> {
 	ptr: *mut c_void,
+	userdata: *mut U,
 	thread: ManuallyDrop<std::thread::JoinHandle<()>>,
-	_userdata: PhantomData<U>,
+	/// Set if the thread exited by itself
+	lost: Arc<AtomicBool>,
 }
 impl<U> WinSdkKinectV2<U> {
 	#[inline]
 	fn new(callback: WinSdkKinectV2Callback<U>, userdata: U) -> Result<Self, std::io::Error> {
 		Self::new_(
 			unsafe { core::mem::transmute::<_, CWinSdkKinectV2Callback>(callback) },
-			Box::into_raw(Box::new(userdata)) as *mut c_void,
+			Box::into_raw(Box::new(userdata)),
 		)
 	}
 
-	fn new_(callback: CWinSdkKinectV2Callback, userdata: *mut c_void) -> Result<Self, std::io::Error> {
+	fn new_(callback: CWinSdkKinectV2Callback, userdata: *mut U) -> Result<Self, std::io::Error> {
 		let mut res = HRESULT(0);
-		let ptr = unsafe { WinSdkKinectV2_Create(callback, userdata, &mut res) };
+		let ptr = unsafe { WinSdkKinectV2_Create(callback, userdata as *mut c_void, &mut res) };
 		if !ptr.is_null() && res.is_ok() {
+			let lost = Arc::new(AtomicBool::new(false));
+
 			Ok(Self {
 				ptr,
+				userdata,
 
 				thread: ManuallyDrop::new({
 					let ptr = SendPtr(ptr);
-					let userdata = SendPtr(userdata);
+					let lost = lost.clone();
 					std::thread::Builder::new()
 						.name("rekinect_winsdk_v2".to_string())
 						.spawn(move || unsafe {
@@ -173,21 +274,24 @@ impl<U> WinSdkKinectV2<U> {
 							let ptr = ptr.0;
 							if let Err(err) = WinSdkKinectV2_Run(ptr).ok() {
 								log::error!("WinSdkKinectV2_Run() failed ({:?})", err);
+								lost.store(true, Ordering::Release);
 							}
-							WinSdkKinectV2_Destroy(ptr);
-
-							let userdata = { userdata };
-							let userdata = userdata.0;
-							drop(Box::from_raw(userdata as *mut U));
 
 							log::info!("WinSdkKinectV2 thread exited");
 						})
 						.unwrap()
 				}),
 
-				_userdata: PhantomData,
+				lost,
 			})
 		} else {
+			unsafe {
+				if !ptr.is_null() {
+					WinSdkKinectV2_Destroy(ptr);
+				}
+				drop(Box::from_raw(userdata));
+			}
+
 			Err(std::io::Error::new(
 				std::io::ErrorKind::Other,
 				format!("WinSdkKinectV2_Create() failed ({res:?})"),
@@ -202,86 +306,116 @@ impl<U> Drop for WinSdkKinectV2<U> {
 			PostThreadMessageW(GetThreadId(HANDLE(thread.as_raw_handle() as isize)), WM_QUIT, WPARAM(0), LPARAM(0)).ok();
 		}
 		thread.join().ok();
+
+		// The thread has exited, so nothing else can be using these anymore
+		unsafe {
+			WinSdkKinectV2_Destroy(self.ptr);
+			drop(Box::from_raw(self.userdata));
+		}
 	}
 }
 
-#[no_mangle]
-pub extern "Rust" fn gmcl_rekinect_init(logger: &'static dyn log::Log) -> Result<Box<dyn KinectBackend>, std::io::Error> {
-	log::set_logger(logger).ok();
-	log::set_max_level(log::LevelFilter::Info);
+kinect::export_backend! {
+	name: "rekinect_winsdk_v2",
+	capabilities: [EXTENDED_JOINTS, ORIENTATIONS, TRACKING_STATES, MULTIPLE_BODIES, SENSOR_TIMESTAMPS, HAND_STATES, FLOOR_PLANE, SENSOR_ORIENTATION],
+	init: init,
+}
+
+fn init(waker: KinectBackendWaker) -> Result<Box<dyn KinectBackend>, std::io::Error> {
+	type Ground = (Option<KinectFloorPlane>, Option<KinectSensorOrientation>);
+
+	#[derive(Default)]
+	struct Mailboxes {
+		bodies: [Mailbox<KinectBody>; BODY_COUNT],
+		ground: Mailbox<Ground>,
+	}
+
+	extern "C" fn callback(event: WinSdkKinectV2SkeletonUpdate, (mailboxes, waker): &mut (Arc<Mailboxes>, KinectBackendWaker)) {
+		// The event points into memory owned by the SDK, so copy it out before handing it over to whoever is polling
+		mailboxes.ground.post(event.ground());
 
-	extern "C" fn callback(event: WinSdkKinectV2SkeletonUpdate, tx: &mut std::sync::mpsc::SyncSender<WinSdkKinectV2SkeletonUpdate>) {
-		tx.send(event).ok();
+		if let Some(mailbox) = mailboxes.bodies.get(event.skeleton_index) {
+			mailbox.post(event.kinect_body());
+			waker.wake();
+		}
 	}
 
-	let (tx, rx) = std::sync::mpsc::sync_channel(1);
-	let kinect = WinSdkKinectV2::new(callback, tx)?;
+	let mailboxes = Arc::new(Mailboxes::default());
+	let kinect = WinSdkKinectV2::new(callback, (mailboxes.clone(), waker))?;
 
 	struct WinSdkKinectBackend {
-		rx: std::sync::mpsc::Receiver<WinSdkKinectV2SkeletonUpdate>,
-		skeleton: Option<usize>,
-		inner: WinSdkKinectV2<std::sync::mpsc::SyncSender<WinSdkKinectV2SkeletonUpdate>>,
+		mailboxes: Arc<Mailboxes>,
+		bodies: [Option<KinectBodyId>; BODY_COUNT],
+		pending: Option<KinectBody>,
+		ground: Ground,
+		inner: WinSdkKinectV2<(Arc<Mailboxes>, KinectBackendWaker)>,
 	}
 	impl KinectBackend for WinSdkKinectBackend {
-		fn poll(&mut self) -> Option<KinectSkeleton> {
-			let event = self.rx.try_recv().ok()?;
-			if self.skeleton.is_none() || self.skeleton == Some(event.skeleton_index) {
-				if let Some(skeleton) = event.skeleton() {
-					self.skeleton = Some(event.skeleton_index);
-
-					let bones = unsafe { &skeleton.named };
-
-					return Some(KinectSkeleton::TrackedExtended(
-						KinectTrackedSkeleton::from_named_bones(KinectSkeletonBones {
-							spine: bones.spine_mid.into_gmod(),
-							hip_center: ((bones.hip_left + bones.hip_right) / 2.0).into_gmod(),
-							shoulder_center: ((bones.shoulder_left + bones.shoulder_right) / 2.0).into_gmod(),
-
-							head: bones.head.into_gmod(),
-							shoulder_left: bones.shoulder_left.into_gmod(),
-							elbow_left: bones.elbow_left.into_gmod(),
-							wrist_left: bones.wrist_left.into_gmod(),
-							hand_left: bones.hand_left.into_gmod(),
-							shoulder_right: bones.shoulder_right.into_gmod(),
-							elbow_right: bones.elbow_right.into_gmod(),
-							wrist_right: bones.wrist_right.into_gmod(),
-							hand_right: bones.hand_right.into_gmod(),
-							hip_left: bones.hip_left.into_gmod(),
-							knee_left: bones.knee_left.into_gmod(),
-							ankle_left: bones.ankle_left.into_gmod(),
-							foot_left: bones.foot_left.into_gmod(),
-							hip_right: bones.hip_right.into_gmod(),
-							knee_right: bones.knee_right.into_gmod(),
-							ankle_right: bones.ankle_right.into_gmod(),
-							foot_right: bones.foot_right.into_gmod(),
-						}),
-						KinectTrackedExtendedSkeleton::from_named_bones(KinectExtendedSkeletonBones {
-							hand_tip_left: bones.hand_tip_left.into_gmod(),
-							thumb_left: bones.thumb_left.into_gmod(),
-							hand_tip_right: bones.hand_tip_right.into_gmod(),
-							thumb_right: bones.thumb_right.into_gmod(),
-							neck: bones.neck.into_gmod(),
-							spine_base: bones.spine_base.into_gmod(),
-							spine_shoulder: bones.spine_shoulder.into_gmod(),
-						}),
-					));
-				} else if self.skeleton.is_some() {
-					self.skeleton = None;
-					return Some(KinectSkeleton::Untracked);
+		fn poll(&mut self) -> Option<KinectBody> {
+			if let Some(ground) = self.mailboxes.ground.take() {
+				self.ground = ground;
+			}
+
+			if let Some(body) = self.pending.take() {
+				return Some(body);
+			}
+
+			for (slot, mailbox) in self.bodies.iter_mut().zip(self.mailboxes.bodies.iter()) {
+				let Some(body) = mailbox.take() else {
+					continue;
+				};
+
+				if let KinectSkeleton::Untracked = body.frame.skeleton {
+					// This body is no longer being tracked
+					if let Some(id) = slot.take() {
+						return Some(KinectBody { id, frame: body.frame });
+					}
+					continue;
+				}
+
+				match slot.replace(body.id) {
+					// The sensor reused this slot for someone else without telling us the previous body was lost
+					Some(id) if id != body.id => {
+						let frame = KinectFrame {
+							skeleton: KinectSkeleton::Untracked,
+							..body.frame
+						};
+						self.pending = Some(body);
+						return Some(KinectBody { id, frame });
+					}
+
+					_ => return Some(body),
 				}
 			}
+
 			None
 		}
 
 		#[inline]
-		fn available(&self) -> bool {
-			unsafe { WinSdkKinectV2_Available(self.inner.ptr) }
+		fn state(&self) -> KinectBackendState {
+			if self.inner.lost.load(Ordering::Acquire) {
+				KinectBackendState::Lost
+			} else {
+				KinectBackendState::from_raw(unsafe { WinSdkKinectV2_State(self.inner.ptr) })
+			}
+		}
+
+		#[inline]
+		fn floor_plane(&self) -> Option<KinectFloorPlane> {
+			self.ground.0
+		}
+
+		#[inline]
+		fn sensor_orientation(&self) -> Option<KinectSensorOrientation> {
+			self.ground.1
 		}
 	}
 
 	Ok(Box::new(WinSdkKinectBackend {
-		rx,
+		mailboxes,
 		inner: kinect,
-		skeleton: None,
+		bodies: [None; BODY_COUNT],
+		pending: None,
+		ground: Ground::default(),
 	}))
 }
@@ -0,0 +1,114 @@
+// C ABI for gmcl_rekinect backend plugins. Mirrors kinect/src/abi.rs, see there for documentation.
+
+#pragma once
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define GMCL_REKINECT_BACKEND_ABI_VERSION 6
+
+#define GMCL_REKINECT_JOINT_COUNT 27
+
+#define GMCL_REKINECT_CAPABILITY_EXTENDED_JOINTS (1u << 0)
+#define GMCL_REKINECT_CAPABILITY_ORIENTATIONS (1u << 1)
+#define GMCL_REKINECT_CAPABILITY_TRACKING_STATES (1u << 2)
+#define GMCL_REKINECT_CAPABILITY_MULTIPLE_BODIES (1u << 3)
+#define GMCL_REKINECT_CAPABILITY_SENSOR_TIMESTAMPS (1u << 4)
+#define GMCL_REKINECT_CAPABILITY_HAND_STATES (1u << 5)
+#define GMCL_REKINECT_CAPABILITY_FLOOR_PLANE (1u << 6)
+#define GMCL_REKINECT_CAPABILITY_SENSOR_ORIENTATION (1u << 7)
+
+#define GMCL_REKINECT_BODY_TRACKED (1u << 0)
+#define GMCL_REKINECT_BODY_EXTENDED (1u << 1)
+#define GMCL_REKINECT_BODY_ORIENTATIONS (1u << 2)
+#define GMCL_REKINECT_BODY_HANDS (1u << 3)
+
+#define GMCL_REKINECT_GROUND_FLOOR (1u << 0)
+#define GMCL_REKINECT_GROUND_UP (1u << 1)
+
+#define GMCL_REKINECT_TRACKING_STATE_NOT_TRACKED 0
+#define GMCL_REKINECT_TRACKING_STATE_INFERRED 1
+#define GMCL_REKINECT_TRACKING_STATE_TRACKED 2
+
+#define GMCL_REKINECT_HAND_STATE_UNKNOWN 0
+#define GMCL_REKINECT_HAND_STATE_NOT_TRACKED 1
+#define GMCL_REKINECT_HAND_STATE_OPEN 2
+#define GMCL_REKINECT_HAND_STATE_CLOSED 3
+#define GMCL_REKINECT_HAND_STATE_LASSO 4
+
+#define GMCL_REKINECT_HAND_CONFIDENCE_LOW 0
+#define GMCL_REKINECT_HAND_CONFIDENCE_HIGH 1
+
+#define GMCL_REKINECT_BACKEND_STATE_NOT_CONNECTED 0
+#define GMCL_REKINECT_BACKEND_STATE_INITIALIZING 1
+#define GMCL_REKINECT_BACKEND_STATE_READY 2
+#define GMCL_REKINECT_BACKEND_STATE_LOST 3
+
+#ifdef _WIN32
+#define GMCL_REKINECT_EXPORT __declspec(dllexport)
+#else
+#define GMCL_REKINECT_EXPORT __attribute__((visibility("default")))
+#endif
+
+#ifdef __cplusplus
+extern "C"
+{
+#endif
+
+	typedef struct KinectBackendInfo
+	{
+		uint32_t abi_version;
+		uint32_t capabilities;
+		const char *name;
+	} KinectBackendInfo;
+
+	typedef struct KinectBackendHost
+	{
+		uint32_t abi_version;
+		void (*log)(uint32_t level, const uint8_t *target, size_t target_len, const uint8_t *message, size_t message_len);
+		// Call with `waker` whenever a new body update is ready to be polled. Safe to call from any thread.
+		void (*wake)(const void *waker);
+		const void *waker;
+	} KinectBackendHost;
+
+	// Joints are indexed by their SENSORBONE number
+	// Everything is in sensor space: metres, x to the sensor's left, y up, z away from the sensor
+	typedef struct KinectBackendBody
+	{
+		uint64_t id;
+		uint64_t sequence;
+		uint64_t timestamp_us;
+		uint32_t flags;
+		float bones[GMCL_REKINECT_JOINT_COUNT][3];
+		uint8_t tracking_states[GMCL_REKINECT_JOINT_COUNT];
+		float orientations[GMCL_REKINECT_JOINT_COUNT][4];
+		// [left, right]
+		uint8_t hand_states[2];
+		uint8_t hand_confidences[2];
+	} KinectBackendBody;
+
+	// A point p is dot(floor_normal, p) + floor_distance above the floor
+	typedef struct KinectBackendGround
+	{
+		uint32_t flags;
+		float floor_normal[3];
+		float floor_distance;
+		float up[3];
+	} KinectBackendGround;
+
+	typedef struct KinectBackendInstance
+	{
+		void *backend;
+		bool (*poll)(void *backend, KinectBackendBody *body);
+		uint32_t (*state)(void *backend);
+		void (*ground)(void *backend, KinectBackendGround *ground);
+		void (*destroy)(void *backend);
+	} KinectBackendInstance;
+
+	GMCL_REKINECT_EXPORT const KinectBackendInfo *gmcl_rekinect_backend_info(void);
+	GMCL_REKINECT_EXPORT bool gmcl_rekinect_backend_create(const KinectBackendHost *host, KinectBackendInstance *out, char *error, size_t error_len);
+
+#ifdef __cplusplus
+}
+#endif
@@ -0,0 +1,545 @@
+//! The C ABI spoken between gmcl_rekinect and its backend plugins.
+//!
+//! A backend is a dynamic library exporting two functions:
+//!
+//! * `const KinectBackendInfo *gmcl_rekinect_backend_info(void)` - the handshake, called before anything else
+//! * `bool gmcl_rekinect_backend_create(const KinectBackendHost *host, KinectBackendInstance *out, char *error, size_t error_len)`
+//!
+//! The host only calls `gmcl_rekinect_backend_create` if the plugin reports the same [`KINECT_BACKEND_ABI_VERSION`] as the host.
+//!
+//! Rust backends should use [`export_backend!`](crate::export_backend), C/C++ backends can include `include/gmcl_rekinect_backend.h`.
+
+use crate::{
+	Joint, KinectBody, KinectBodyId, KinectFloorPlane, KinectFrame, KinectHand, KinectHandConfidence, KinectHandState, KinectHands,
+	KinectSensorOrientation, KinectSkeleton, KinectTrackedExtendedSkeleton, KinectTrackedSkeleton, KinectTrackingState,
+};
+use std::{
+	ffi::{c_char, c_void, CStr},
+	sync::{Condvar, Mutex, OnceLock, PoisonError},
+	time::Duration,
+};
+
+/// Bumped whenever any type in this module changes layout or meaning.
+pub const KINECT_BACKEND_ABI_VERSION: u32 = 6;
+
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct KinectBackendCapabilities(pub u32);
+impl KinectBackendCapabilities {
+	pub const NONE: Self = Self(0);
+	/// The backend reports [`Joint::is_extended`] joints.
+	pub const EXTENDED_JOINTS: Self = Self(1 << 0);
+	/// The backend reports joint orientations.
+	pub const ORIENTATIONS: Self = Self(1 << 1);
+	/// The backend reports a [`KinectTrackingState`] for each joint rather than always reporting them as tracked.
+	pub const TRACKING_STATES: Self = Self(1 << 2);
+	/// The backend can track more than one body at a time.
+	pub const MULTIPLE_BODIES: Self = Self(1 << 3);
+	/// The backend timestamps frames using the sensor's own clock.
+	pub const SENSOR_TIMESTAMPS: Self = Self(1 << 4);
+	/// The backend reports [`KinectHands`].
+	pub const HAND_STATES: Self = Self(1 << 5);
+	/// The backend reports a [`KinectFloorPlane`].
+	pub const FLOOR_PLANE: Self = Self(1 << 6);
+	/// The backend reports a [`KinectSensorOrientation`].
+	pub const SENSOR_ORIENTATION: Self = Self(1 << 7);
+
+	const NAMES: &'static [(Self, &'static str)] = &[
+		(Self::EXTENDED_JOINTS, "EXTENDED_JOINTS"),
+		(Self::ORIENTATIONS, "ORIENTATIONS"),
+		(Self::TRACKING_STATES, "TRACKING_STATES"),
+		(Self::MULTIPLE_BODIES, "MULTIPLE_BODIES"),
+		(Self::SENSOR_TIMESTAMPS, "SENSOR_TIMESTAMPS"),
+		(Self::HAND_STATES, "HAND_STATES"),
+		(Self::FLOOR_PLANE, "FLOOR_PLANE"),
+		(Self::SENSOR_ORIENTATION, "SENSOR_ORIENTATION"),
+	];
+
+	#[inline]
+	pub const fn contains(self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+}
+impl std::ops::BitOr for KinectBackendCapabilities {
+	type Output = Self;
+
+	#[inline]
+	fn bitor(self, rhs: Self) -> Self::Output {
+		Self(self.0 | rhs.0)
+	}
+}
+impl std::fmt::Debug for KinectBackendCapabilities {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_set()
+			.entries(
+				Self::NAMES
+					.iter()
+					.filter(|(capability, _)| self.contains(*capability))
+					.map(|(_, name)| name),
+			)
+			.finish()
+	}
+}
+
+/// Returned by `gmcl_rekinect_backend_info`. Must live for as long as the library is loaded.
+#[repr(C)]
+pub struct KinectBackendInfo {
+	pub abi_version: u32,
+	pub capabilities: KinectBackendCapabilities,
+	/// NUL-terminated UTF-8
+	pub name: *const c_char,
+}
+unsafe impl Sync for KinectBackendInfo {}
+impl KinectBackendInfo {
+	#[inline]
+	pub fn name(&self) -> &str {
+		if self.name.is_null() {
+			return "";
+		}
+		unsafe { CStr::from_ptr(self.name) }.to_str().unwrap_or_default()
+	}
+}
+
+/// Services the host provides to the backend.
+#[repr(C)]
+pub struct KinectBackendHost {
+	pub abi_version: u32,
+	/// `level` uses the same numbering as [`log::Level`]. Neither string is NUL-terminated.
+	pub log: unsafe extern "C" fn(level: u32, target: *const u8, target_len: usize, message: *const u8, message_len: usize),
+	/// Call with `waker` whenever there is something new to poll. Safe to call from any thread until the backend is destroyed.
+	pub wake: unsafe extern "C" fn(waker: *const c_void),
+	pub waker: *const c_void,
+}
+
+/// Lets a backend tell the host there is something new to poll, see [`KinectBackendHost::wake`].
+#[derive(Clone, Copy)]
+pub struct KinectBackendWaker {
+	wake: unsafe extern "C" fn(*const c_void),
+	waker: *const c_void,
+}
+unsafe impl Send for KinectBackendWaker {}
+unsafe impl Sync for KinectBackendWaker {}
+impl KinectBackendWaker {
+	#[inline]
+	pub fn wake(&self) {
+		unsafe { (self.wake)(self.waker) }
+	}
+}
+impl std::fmt::Debug for KinectBackendWaker {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("KinectBackendWaker").field("waker", &self.waker).finish()
+	}
+}
+
+/// Filled in by `gmcl_rekinect_backend_create`.
+#[repr(C)]
+pub struct KinectBackendInstance {
+	pub backend: *mut c_void,
+	/// Writes the next body update to `body` and returns `true`, or returns `false` if there are no updates.
+	pub poll: unsafe extern "C" fn(backend: *mut c_void, body: *mut KinectBackendBody) -> bool,
+	/// Returns a [`KinectBackendState`](crate::KinectBackendState).
+	pub state: unsafe extern "C" fn(backend: *mut c_void) -> u32,
+	/// Writes the latest floor plane and sensor orientation to `ground`.
+	pub ground: unsafe extern "C" fn(backend: *mut c_void, ground: *mut KinectBackendGround),
+	pub destroy: unsafe extern "C" fn(backend: *mut c_void),
+}
+
+/// A [`KinectFloorPlane`] and [`KinectSensorOrientation`] laid out for the C ABI, in sensor space.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KinectBackendGround {
+	pub flags: u32,
+	pub floor_normal: [f32; 3],
+	pub floor_distance: f32,
+	pub up: [f32; 3],
+}
+impl KinectBackendGround {
+	/// `floor_normal` and `floor_distance` are valid.
+	pub const FLOOR: u32 = 1 << 0;
+	/// `up` is valid.
+	pub const UP: u32 = 1 << 1;
+
+	pub fn new(floor: Option<KinectFloorPlane>, orientation: Option<KinectSensorOrientation>) -> Self {
+		let mut ground = Self::default();
+
+		if let Some(floor) = floor {
+			ground.flags |= Self::FLOOR;
+			ground.floor_normal = floor.normal;
+			ground.floor_distance = floor.distance;
+		}
+
+		if let Some(orientation) = orientation {
+			ground.flags |= Self::UP;
+			ground.up = orientation.up;
+		}
+
+		ground
+	}
+
+	#[inline]
+	pub fn floor_plane(&self) -> Option<KinectFloorPlane> {
+		if self.flags & Self::FLOOR != 0 {
+			KinectFloorPlane::new(self.floor_normal, self.floor_distance)
+		} else {
+			None
+		}
+	}
+
+	#[inline]
+	pub fn sensor_orientation(&self) -> Option<KinectSensorOrientation> {
+		if self.flags & Self::UP != 0 {
+			KinectSensorOrientation::new(self.up)
+		} else {
+			None
+		}
+	}
+}
+
+/// A [`KinectBody`] laid out for the C ABI. Joints are indexed by their `SENSORBONE` number.
+///
+/// Backends report everything in sensor space (see [`KinectAxis`](crate::KinectAxis)), [`Kinect`](crate::Kinect) takes care of
+/// converting it with its [`KinectTransform`](crate::KinectTransform).
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct KinectBackendBody {
+	pub id: u64,
+	pub sequence: u64,
+	pub timestamp_us: u64,
+	pub flags: u32,
+	pub bones: [[f32; 3]; Joint::COUNT],
+	/// See [`KinectTrackingState`]
+	pub tracking_states: [u8; Joint::COUNT],
+	/// `[x, y, z, w]` quaternions
+	pub orientations: [[f32; 4]; Joint::COUNT],
+	/// `[left, right]`, see [`KinectHandState`]
+	pub hand_states: [u8; 2],
+	/// `[left, right]`, see [`KinectHandConfidence`]
+	pub hand_confidences: [u8; 2],
+}
+impl KinectBackendBody {
+	/// The body is being tracked. If unset, the body was lost and everything but `id`, `sequence` and `timestamp_us` is ignored.
+	pub const TRACKED: u32 = 1 << 0;
+	/// The extended joints are valid.
+	pub const EXTENDED: u32 = 1 << 1;
+	/// `orientations` is valid.
+	pub const ORIENTATIONS: u32 = 1 << 2;
+	/// `hand_states` and `hand_confidences` are valid.
+	pub const HANDS: u32 = 1 << 3;
+}
+impl Default for KinectBackendBody {
+	fn default() -> Self {
+		Self {
+			id: 0,
+			sequence: 0,
+			timestamp_us: 0,
+			flags: 0,
+			bones: [[0.0; 3]; Joint::COUNT],
+			tracking_states: [KinectTrackingState::NotTracked as u8; Joint::COUNT],
+			orientations: [[0.0, 0.0, 0.0, 1.0]; Joint::COUNT],
+			hand_states: [KinectHandState::Unknown as u8; 2],
+			hand_confidences: [KinectHandConfidence::Low as u8; 2],
+		}
+	}
+}
+impl From<&KinectBody> for KinectBackendBody {
+	fn from(body: &KinectBody) -> Self {
+		let skeleton = &body.frame.skeleton;
+
+		let mut raw = Self {
+			id: body.id.0,
+			sequence: body.frame.sequence,
+			timestamp_us: body.frame.timestamp.as_micros() as u64,
+			..Default::default()
+		};
+
+		if let Some(tracked) = skeleton.tracked() {
+			raw.flags |= Self::TRACKED;
+
+			if tracked.orientations().is_some() {
+				raw.flags |= Self::ORIENTATIONS;
+			}
+
+			if let Some(hands) = tracked.hands() {
+				raw.flags |= Self::HANDS;
+				raw.hand_states = [hands.left.state as u8, hands.right.state as u8];
+				raw.hand_confidences = [hands.left.confidence as u8, hands.right.confidence as u8];
+			}
+		}
+
+		if skeleton.extended().is_some() {
+			raw.flags |= Self::EXTENDED;
+		}
+
+		for joint in Joint::iter() {
+			let i = joint.sensor_bone();
+
+			if let Some(bone) = skeleton.bone(joint) {
+				raw.bones[i] = *bone;
+			}
+			if let Some(orientation) = skeleton.orientation(joint) {
+				raw.orientations[i] = *orientation;
+			}
+			raw.tracking_states[i] = skeleton.tracking_state(joint) as u8;
+		}
+
+		raw
+	}
+}
+impl From<&KinectBackendBody> for KinectBody {
+	fn from(raw: &KinectBackendBody) -> Self {
+		let bone = |joint: Joint| raw.bones[joint.sensor_bone()];
+		let tracking_state = |joint: Joint| KinectTrackingState::from_raw(raw.tracking_states[joint.sensor_bone()]);
+		let orientation = |joint: Joint| raw.orientations[joint.sensor_bone()];
+
+		let skeleton = if raw.flags & KinectBackendBody::TRACKED == 0 {
+			KinectSkeleton::Untracked
+		} else {
+			let mut skeleton = KinectTrackedSkeleton::from_raw_bones(Joint::SKELETON.map(bone), Joint::SKELETON.map(tracking_state));
+
+			if raw.flags & KinectBackendBody::ORIENTATIONS != 0 {
+				skeleton = skeleton.with_orientations(Joint::SKELETON.map(orientation));
+			}
+
+			if raw.flags & KinectBackendBody::HANDS != 0 {
+				let hand = |i: usize| KinectHand {
+					state: KinectHandState::from_raw(raw.hand_states[i]),
+					confidence: KinectHandConfidence::from_raw(raw.hand_confidences[i]),
+				};
+
+				skeleton = skeleton.with_hands(KinectHands {
+					left: hand(0),
+					right: hand(1),
+				});
+			}
+
+			if raw.flags & KinectBackendBody::EXTENDED != 0 {
+				let mut extended = KinectTrackedExtendedSkeleton::from_raw_bones(Joint::EXTENDED.map(bone), Joint::EXTENDED.map(tracking_state));
+
+				if raw.flags & KinectBackendBody::ORIENTATIONS != 0 {
+					extended = extended.with_orientations(Joint::EXTENDED.map(orientation));
+				}
+
+				KinectSkeleton::TrackedExtended(skeleton, extended)
+			} else {
+				KinectSkeleton::Tracked(skeleton)
+			}
+		};
+
+		KinectBody {
+			id: KinectBodyId(raw.id),
+			frame: KinectFrame {
+				skeleton,
+				timestamp: Duration::from_micros(raw.timestamp_us),
+				sequence: raw.sequence,
+			},
+		}
+	}
+}
+
+pub type KinectBackendInfoFn = unsafe extern "C" fn() -> *const KinectBackendInfo;
+pub type KinectBackendCreateFn =
+	unsafe extern "C" fn(host: *const KinectBackendHost, out: *mut KinectBackendInstance, error: *mut c_char, error_len: usize) -> bool;
+
+/// Exports a Rust [`KinectBackend`](crate::KinectBackend) over the C ABI.
+///
+/// ```ignore
+/// kinect::export_backend! {
+///     name: "my_backend",
+///     capabilities: [ORIENTATIONS, TRACKING_STATES],
+///     init: my_backend_init, // fn(KinectBackendWaker) -> Result<Box<dyn KinectBackend>, std::io::Error>
+/// }
+/// ```
+#[macro_export]
+macro_rules! export_backend {
+	{name: $name:literal, capabilities: [$($capability:ident),* $(,)?], init: $init:path $(,)?} => {
+		#[no_mangle]
+		pub extern "C" fn gmcl_rekinect_backend_info() -> *const $crate::abi::KinectBackendInfo {
+			static INFO: $crate::abi::KinectBackendInfo = $crate::abi::KinectBackendInfo {
+				abi_version: $crate::abi::KINECT_BACKEND_ABI_VERSION,
+				capabilities: $crate::abi::KinectBackendCapabilities(0 $(| $crate::abi::KinectBackendCapabilities::$capability.0)*),
+				name: concat!($name, "\0").as_ptr() as *const _,
+			};
+			&INFO
+		}
+
+		#[no_mangle]
+		pub unsafe extern "C" fn gmcl_rekinect_backend_create(
+			host: *const $crate::abi::KinectBackendHost,
+			out: *mut $crate::abi::KinectBackendInstance,
+			error: *mut ::std::ffi::c_char,
+			error_len: usize,
+		) -> bool {
+			$crate::abi::create_backend(host, out, error, error_len, $init)
+		}
+	};
+}
+
+/// Plugin side of `gmcl_rekinect_backend_create`, see [`export_backend!`](crate::export_backend).
+#[doc(hidden)]
+pub unsafe fn create_backend(
+	host: *const KinectBackendHost,
+	out: *mut KinectBackendInstance,
+	error: *mut c_char,
+	error_len: usize,
+	init: fn(KinectBackendWaker) -> Result<Box<dyn crate::KinectBackend>, std::io::Error>,
+) -> bool {
+	let host = &*host;
+
+	if host.abi_version != KINECT_BACKEND_ABI_VERSION {
+		write_error(error, error_len, &format!("host ABI version {} is not supported", host.abi_version));
+		return false;
+	}
+
+	static LOGGER: OnceLock<HostLogger> = OnceLock::new();
+	log::set_logger(LOGGER.get_or_init(|| HostLogger(host.log))).ok();
+	log::set_max_level(log::LevelFilter::Info);
+
+	let waker = KinectBackendWaker {
+		wake: host.wake,
+		waker: host.waker,
+	};
+
+	match init(waker) {
+		Ok(backend) => {
+			unsafe extern "C" fn poll(backend: *mut c_void, body: *mut KinectBackendBody) -> bool {
+				match (*(backend as *mut Box<dyn crate::KinectBackend>)).poll() {
+					Some(update) => {
+						*body = KinectBackendBody::from(&update);
+						true
+					}
+					None => false,
+				}
+			}
+
+			unsafe extern "C" fn state(backend: *mut c_void) -> u32 {
+				(*(backend as *mut Box<dyn crate::KinectBackend>)).state() as u32
+			}
+
+			unsafe extern "C" fn ground(backend: *mut c_void, ground: *mut KinectBackendGround) {
+				let backend = &*(backend as *mut Box<dyn crate::KinectBackend>);
+				*ground = KinectBackendGround::new(backend.floor_plane(), backend.sensor_orientation());
+			}
+
+			unsafe extern "C" fn destroy(backend: *mut c_void) {
+				drop(Box::from_raw(backend as *mut Box<dyn crate::KinectBackend>));
+			}
+
+			out.write(KinectBackendInstance {
+				backend: Box::into_raw(Box::new(backend)) as *mut c_void,
+				poll,
+				state,
+				ground,
+				destroy,
+			});
+
+			true
+		}
+
+		Err(err) => {
+			write_error(error, error_len, &format!("{err:?}"));
+			false
+		}
+	}
+}
+
+unsafe fn write_error(error: *mut c_char, error_len: usize, message: &str) {
+	if error.is_null() || error_len == 0 {
+		return;
+	}
+
+	// Never cut a character in half, the host expects UTF-8
+	let mut len = message.len().min(error_len - 1);
+	while !message.is_char_boundary(len) {
+		len -= 1;
+	}
+
+	core::ptr::copy_nonoverlapping(message.as_ptr(), error as *mut u8, len);
+	*error.add(len) = 0;
+}
+
+/// Forwards a plugin's log records to the host.
+struct HostLogger(unsafe extern "C" fn(u32, *const u8, usize, *const u8, usize));
+impl log::Log for HostLogger {
+	#[inline]
+	fn enabled(&self, metadata: &log::Metadata) -> bool {
+		metadata.level() <= log::max_level()
+	}
+
+	fn log(&self, record: &log::Record) {
+		if !self.enabled(record.metadata()) {
+			return;
+		}
+
+		let message = record.args().to_string();
+		let target = record.target();
+		unsafe { (self.0)(record.level() as u32, target.as_ptr(), target.len(), message.as_ptr(), message.len()) };
+	}
+
+	fn flush(&self) {}
+}
+
+/// Host side of [`KinectBackendHost::log`].
+pub(crate) unsafe extern "C" fn host_log(level: u32, target: *const u8, target_len: usize, message: *const u8, message_len: usize) {
+	let level = match level {
+		1 => log::Level::Error,
+		2 => log::Level::Warn,
+		3 => log::Level::Info,
+		4 => log::Level::Debug,
+		_ => log::Level::Trace,
+	};
+
+	let target = std::str::from_utf8(std::slice::from_raw_parts(target, target_len)).unwrap_or("backend");
+	let message = String::from_utf8_lossy(std::slice::from_raw_parts(message, message_len));
+
+	log::logger().log(&log::Record::builder().level(level).target(target).args(format_args!("{message}")).build());
+}
+
+/// Host side of [`KinectBackendHost::wake`]. Wakes up anything waiting in [`Kinect::wait`](crate::Kinect::wait).
+#[derive(Default)]
+pub(crate) struct HostWaker {
+	woken: Mutex<bool>,
+	condvar: Condvar,
+}
+impl HostWaker {
+	pub(crate) fn wake(&self) {
+		*self.woken.lock().unwrap_or_else(PoisonError::into_inner) = true;
+		self.condvar.notify_all();
+	}
+
+	/// Returns `true` if woken, or `false` if timed out.
+	pub(crate) fn wait(&self, timeout: Option<Duration>) -> bool {
+		let woken = self.woken.lock().unwrap_or_else(PoisonError::into_inner);
+
+		let mut woken = match timeout {
+			Some(timeout) => {
+				self.condvar
+					.wait_timeout_while(woken, timeout, |woken| !*woken)
+					.unwrap_or_else(PoisonError::into_inner)
+					.0
+			}
+			None => self.condvar.wait_while(woken, |woken| !*woken).unwrap_or_else(PoisonError::into_inner),
+		};
+
+		std::mem::take(&mut *woken)
+	}
+}
+
+pub(crate) unsafe extern "C" fn host_wake(waker: *const c_void) {
+	(*(waker as *const HostWaker)).wake();
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn write_error_keeps_whole_characters() {
+		let mut buffer = [0x7f as c_char; 5];
+		unsafe { write_error(buffer.as_mut_ptr(), buffer.len(), "ab\u{e9}\u{e9}") };
+		assert_eq!(unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) }.to_str(), Ok("ab\u{e9}"));
+
+		let mut buffer = [0x7f as c_char; 4];
+		unsafe { write_error(buffer.as_mut_ptr(), buffer.len(), "ab\u{e9}\u{e9}") };
+		assert_eq!(unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) }.to_str(), Ok("ab"));
+	}
+}
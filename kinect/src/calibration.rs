@@ -0,0 +1,388 @@
+use crate::{
+	floor,
+	math::{self, Vec3},
+	Joint, KinectBodyId, KinectSkeleton, KinectTrackingState,
+};
+use std::{
+	path::Path,
+	time::{Duration, Instant},
+};
+
+/// Where Garry's Mod keeps the calibrated [`KinectBodyProfile`].
+pub const BODY_PROFILE_PATH: &str = "garrysmod/data/rekinect/profile.txt";
+
+/// The pose held while calibrating. Either way, stand up straight facing the sensor with both arms straight.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum KinectCalibrationPose {
+	/// Arms held out to the sides, level with the shoulders.
+	#[default]
+	T,
+
+	/// Arms held down and out to the sides, roughly 45° from the body.
+	A,
+}
+impl KinectCalibrationPose {
+	#[inline]
+	pub const fn name(self) -> &'static str {
+		match self {
+			KinectCalibrationPose::T => "T",
+			KinectCalibrationPose::A => "A",
+		}
+	}
+
+	/// Looks up a pose by its [`name`](Self::name), ignoring case.
+	#[inline]
+	pub fn from_name(name: &str) -> Option<Self> {
+		[Self::T, Self::A].into_iter().find(|pose| pose.name().eq_ignore_ascii_case(name))
+	}
+
+	/// Whether `skeleton` is holding this pose, with both arms fully tracked.
+	fn held_by(self, skeleton: &KinectSkeleton) -> bool {
+		let Some(center) = skeleton.bone(Joint::ShoulderCenter) else {
+			return false;
+		};
+
+		let arm = |[shoulder, elbow, wrist]: [Joint; 3]| {
+			if [shoulder, elbow, wrist]
+				.into_iter()
+				.any(|joint| skeleton.tracking_state(joint) != KinectTrackingState::Tracked)
+			{
+				return false;
+			}
+
+			let (Some(&shoulder), Some(&elbow), Some(&wrist)) = (skeleton.bone(shoulder), skeleton.bone(elbow), skeleton.bone(wrist)) else {
+				return false;
+			};
+
+			let reach = math::sub(wrist, shoulder);
+			let limb = math::length(math::sub(elbow, shoulder)) + math::length(math::sub(wrist, elbow));
+			let (Some(direction), Some(outwards)) = (math::normalize(reach), math::normalize(math::sub(shoulder, *center))) else {
+				return false;
+			};
+
+			let vertical = math::dot(direction, floor::UP);
+			let straight = math::length(reach) >= limb * 0.9;
+			let sideways = math::dot(direction, outwards) > 0.3;
+
+			let aimed = match self {
+				KinectCalibrationPose::T => vertical.abs() < 0.35,
+				KinectCalibrationPose::A => (-0.9..-0.4).contains(&vertical),
+			};
+
+			straight && sideways && aimed
+		};
+
+		arm([Joint::ShoulderLeft, Joint::ElbowLeft, Joint::WristLeft]) && arm([Joint::ShoulderRight, Joint::ElbowRight, Joint::WristRight])
+	}
+}
+
+/// Where [`Kinect::calibrate`](crate::Kinect::calibrate) is up to.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum KinectCalibrationState {
+	#[default]
+	Idle,
+
+	/// `progress` goes from 0 to 1 over the capture.
+	Capturing { pose: KinectCalibrationPose, progress: f32 },
+
+	/// The capture finished and its profile is now being applied, see [`Kinect::profile`](crate::Kinect::profile).
+	Calibrated,
+
+	/// Nobody held the pose for long enough, so the previous profile (if any) is still being applied.
+	Failed,
+}
+
+/// A calibration finished, see [`Kinect::take_calibrations`](crate::Kinect::take_calibrations).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectCalibrationEvent {
+	/// The body that held the pose, if anyone did. The new profile is only applied to this body.
+	pub body: Option<KinectBodyId>,
+	/// The new profile, or `None` if nobody held the pose for long enough.
+	pub profile: Option<KinectBodyProfile>,
+}
+
+/// A user's proportions, measured by [`Kinect::calibrate`](crate::Kinect::calibrate).
+///
+/// While a profile is set, the body it was measured from is normalised to the proportions of [`KinectBodyProfile::REFERENCE`] and
+/// re-centred horizontally on where the user stood while calibrating, before the [`KinectTransform`](crate::KinectTransform) is applied.
+/// Profiles set with [`Kinect::set_profile`](crate::Kinect::set_profile) are applied to the selected body instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectBodyProfile {
+	pub pose: KinectCalibrationPose,
+	/// How far each joint is from its [`Joint::parent`], in metres. Zero if it was never seen.
+	pub segment_lengths: [f32; Joint::COUNT],
+	/// The distance between the shoulders, in metres.
+	pub shoulder_width: f32,
+	/// Where the hips were while calibrating, in the [`KinectReferenceFrame`](crate::KinectReferenceFrame) that was in use.
+	pub origin: [f32; 3],
+}
+impl KinectBodyProfile {
+	/// Roughly an average adult, 1.75m tall.
+	pub const REFERENCE: Self = {
+		let mut segment_lengths = [0.0; Joint::COUNT];
+		segment_lengths[Joint::Spine.sensor_bone()] = 0.1;
+		segment_lengths[Joint::ShoulderCenter.sensor_bone()] = 0.35;
+		segment_lengths[Joint::Head.sensor_bone()] = 0.2;
+		segment_lengths[Joint::ShoulderLeft.sensor_bone()] = 0.17;
+		segment_lengths[Joint::ElbowLeft.sensor_bone()] = 0.28;
+		segment_lengths[Joint::WristLeft.sensor_bone()] = 0.25;
+		segment_lengths[Joint::HandLeft.sensor_bone()] = 0.08;
+		segment_lengths[Joint::ShoulderRight.sensor_bone()] = 0.17;
+		segment_lengths[Joint::ElbowRight.sensor_bone()] = 0.28;
+		segment_lengths[Joint::WristRight.sensor_bone()] = 0.25;
+		segment_lengths[Joint::HandRight.sensor_bone()] = 0.08;
+		segment_lengths[Joint::HipLeft.sensor_bone()] = 0.08;
+		segment_lengths[Joint::KneeLeft.sensor_bone()] = 0.42;
+		segment_lengths[Joint::AnkleLeft.sensor_bone()] = 0.4;
+		segment_lengths[Joint::FootLeft.sensor_bone()] = 0.12;
+		segment_lengths[Joint::HipRight.sensor_bone()] = 0.08;
+		segment_lengths[Joint::KneeRight.sensor_bone()] = 0.42;
+		segment_lengths[Joint::AnkleRight.sensor_bone()] = 0.4;
+		segment_lengths[Joint::FootRight.sensor_bone()] = 0.12;
+		segment_lengths[Joint::SpineBase.sensor_bone()] = 0.05;
+		segment_lengths[Joint::Neck.sensor_bone()] = 0.08;
+		segment_lengths[Joint::SpineShoulder.sensor_bone()] = 0.02;
+		segment_lengths[Joint::HandTipLeft.sensor_bone()] = 0.08;
+		segment_lengths[Joint::ThumbLeft.sensor_bone()] = 0.05;
+		segment_lengths[Joint::HandTipRight.sensor_bone()] = 0.08;
+		segment_lengths[Joint::ThumbRight.sensor_bone()] = 0.05;
+
+		Self {
+			pose: KinectCalibrationPose::T,
+			segment_lengths,
+			shoulder_width: 0.34,
+			origin: [0.0; 3],
+		}
+	};
+
+	/// How far `joint` is from its [`Joint::parent`], in metres, if it was measured.
+	#[inline]
+	pub fn segment_length(&self, joint: Joint) -> Option<f32> {
+		Some(self.segment_lengths[joint.sensor_bone()]).filter(|length| *length > 0.0)
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+		std::fs::read_to_string(path)?.parse()
+	}
+
+	/// Creates the parent directory if needed.
+	pub fn save(&self, path: impl AsRef<Path>) -> Result<(), std::io::Error> {
+		let path = path.as_ref();
+		if let Some(dir) = path.parent() {
+			std::fs::create_dir_all(dir)?;
+		}
+		std::fs::write(path, self.to_string())
+	}
+
+	/// Scales every segment to [`KinectBodyProfile::REFERENCE`]'s length for it, keeping its direction, working outwards from the hips.
+	pub(crate) fn normalize(&self, skeleton: &mut KinectSkeleton) {
+		let original = Joint::ALL.map(|joint| skeleton.bone(joint).copied());
+		let mut normalized = original;
+
+		for joint in Joint::ALL {
+			let Some(position) = original[joint.sensor_bone()] else {
+				continue;
+			};
+
+			normalized[joint.sensor_bone()] = Some(
+				match joint
+					.parent()
+					.and_then(|parent| Some((original[parent.sensor_bone()]?, normalized[parent.sensor_bone()]?)))
+				{
+					Some((parent, normalized_parent)) => {
+						let ratio = match (self.segment_length(joint), Self::REFERENCE.segment_length(joint)) {
+							(Some(length), Some(reference)) => reference / length,
+							_ => 1.0,
+						};
+						math::add(normalized_parent, math::scale(math::sub(position, parent), ratio))
+					}
+
+					// Keep the height, so the floor stays where it is
+					None => math::sub(position, [self.origin[0], 0.0, self.origin[2]]),
+				},
+			);
+		}
+
+		for joint in Joint::ALL {
+			if let (Some(bone), Some(normalized)) = (skeleton.bone_mut(joint), normalized[joint.sensor_bone()]) {
+				*bone = normalized;
+			}
+		}
+	}
+}
+impl std::fmt::Display for KinectBodyProfile {
+	/// One `key value...` per line, which [`str::parse`] reads back.
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(f, "# gmcl_rekinect body profile, lengths are in metres")?;
+		writeln!(f, "pose {}", self.pose.name())?;
+		writeln!(f, "shoulder_width {}", self.shoulder_width)?;
+		writeln!(f, "origin {} {} {}", self.origin[0], self.origin[1], self.origin[2])?;
+
+		for joint in Joint::ALL {
+			if let Some(length) = self.segment_length(joint) {
+				writeln!(f, "{} {}", joint.name(), length)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+impl std::str::FromStr for KinectBodyProfile {
+	type Err = std::io::Error;
+
+	/// Blank lines, lines starting with `#` and unknown keys are ignored.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = |line: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Invalid body profile line: {line:?}"));
+
+		let mut profile = Self {
+			pose: KinectCalibrationPose::default(),
+			segment_lengths: [0.0; Joint::COUNT],
+			shoulder_width: 0.0,
+			origin: [0.0; 3],
+		};
+
+		for line in s.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+			let mut words = line.split_whitespace();
+			let key = words.next().unwrap_or_default();
+
+			let mut numbers = words.clone().map(str::parse::<f32>);
+			let mut number = || {
+				numbers
+					.next()
+					.and_then(Result::ok)
+					.filter(|number| number.is_finite())
+					.ok_or_else(|| invalid(line))
+			};
+
+			match key {
+				"pose" => profile.pose = words.next().and_then(KinectCalibrationPose::from_name).ok_or_else(|| invalid(line))?,
+				"shoulder_width" => profile.shoulder_width = number()?,
+				"origin" => profile.origin = [number()?, number()?, number()?],
+				_ => {
+					if let Some(joint) = Joint::from_name(key) {
+						profile.segment_lengths[joint.sensor_bone()] = number()?.max(0.0);
+					}
+				}
+			}
+		}
+
+		Ok(profile)
+	}
+}
+
+/// Averages one body's proportions while it holds the pose. That's the first body to hold it, unless only the selected body is sampled.
+pub(crate) struct KinectCalibration {
+	pose: KinectCalibrationPose,
+	started: Instant,
+	duration: Duration,
+	/// The first body that held the pose, the only one sampled from then on.
+	body: Option<KinectBodyId>,
+	frames: u32,
+	held: u32,
+	segment_lengths: [f32; Joint::COUNT],
+	segment_samples: [u32; Joint::COUNT],
+	shoulder_width: f32,
+	origin: Vec3,
+}
+impl KinectCalibration {
+	/// The pose has to be held for at least this many frames...
+	const MIN_FRAMES: u32 = 15;
+	/// ...and at least this much of the capture.
+	const MIN_HELD: f32 = 0.5;
+
+	pub(crate) fn new(pose: KinectCalibrationPose, duration: Duration, now: Instant) -> Self {
+		Self {
+			pose,
+			started: now,
+			duration,
+			body: None,
+			frames: 0,
+			held: 0,
+			segment_lengths: [0.0; Joint::COUNT],
+			segment_samples: [0; Joint::COUNT],
+			shoulder_width: 0.0,
+			origin: [0.0; 3],
+		}
+	}
+
+	pub(crate) fn state(&self, now: Instant) -> KinectCalibrationState {
+		KinectCalibrationState::Capturing {
+			pose: self.pose,
+			progress: if self.duration.is_zero() {
+				1.0
+			} else {
+				((now - self.started).as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+			},
+		}
+	}
+
+	#[inline]
+	pub(crate) fn finished(&self, now: Instant) -> bool {
+		now - self.started >= self.duration
+	}
+
+	#[inline]
+	pub(crate) fn body(&self) -> Option<KinectBodyId> {
+		self.body
+	}
+
+	pub(crate) fn sample(&mut self, body: KinectBodyId, skeleton: &KinectSkeleton) {
+		if matches!(skeleton, KinectSkeleton::Untracked) || self.body.is_some_and(|id| id != body) {
+			return;
+		}
+
+		self.frames += 1;
+
+		if !self.pose.held_by(skeleton) {
+			return;
+		}
+
+		let (Some(&hips), Some(&left), Some(&right)) = (
+			skeleton.bone(Joint::HipCenter),
+			skeleton.bone(Joint::ShoulderLeft),
+			skeleton.bone(Joint::ShoulderRight),
+		) else {
+			return;
+		};
+
+		self.held += 1;
+		self.body = Some(body);
+		self.origin = math::add(self.origin, hips);
+		self.shoulder_width += math::length(math::sub(left, right));
+
+		for joint in Joint::ALL {
+			let Some(parent) = joint.parent() else {
+				continue;
+			};
+
+			if skeleton.tracking_state(joint) != KinectTrackingState::Tracked || skeleton.tracking_state(parent) != KinectTrackingState::Tracked {
+				continue;
+			}
+
+			if let (Some(&position), Some(&parent)) = (skeleton.bone(joint), skeleton.bone(parent)) {
+				self.segment_lengths[joint.sensor_bone()] += math::length(math::sub(position, parent));
+				self.segment_samples[joint.sensor_bone()] += 1;
+			}
+		}
+	}
+
+	/// Returns `None` if the pose wasn't held for long enough.
+	pub(crate) fn finish(self) -> Option<KinectBodyProfile> {
+		if self.held < Self::MIN_FRAMES || (self.held as f32) < self.frames as f32 * Self::MIN_HELD {
+			return None;
+		}
+
+		let mut segment_lengths = self.segment_lengths;
+		for (length, samples) in segment_lengths.iter_mut().zip(self.segment_samples) {
+			*length = if samples != 0 { *length / samples as f32 } else { 0.0 };
+		}
+
+		let held = 1.0 / self.held as f32;
+		Some(KinectBodyProfile {
+			pose: self.pose,
+			segment_lengths,
+			shoulder_width: self.shoulder_width * held,
+			origin: math::scale(self.origin, held),
+		})
+	}
+}
@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding extra backend search paths, separated like `PATH`.
+pub const BACKEND_PATH_ENV: &str = "GMCL_REKINECT_BACKEND_PATH";
+
+/// Config file listing extra backend search paths, one per line. Blank lines and lines starting with `#` are ignored.
+pub const BACKEND_PATH_CONFIG: &str = "garrysmod/data/rekinect/backends.txt";
+
+/// Directories scanned for backends after everything else.
+pub const BACKEND_PLUGIN_DIRS: &[&str] = &["garrysmod/lua/bin", "."];
+
+/// The platform suffix Garry's Mod uses for binary modules, e.g. `win64`.
+pub const PLATFORM: &str = if cfg!(all(windows, target_pointer_width = "64")) {
+	"win64"
+} else if cfg!(windows) {
+	"win32"
+} else if cfg!(all(target_os = "macos", target_pointer_width = "64")) {
+	"osx64"
+} else if cfg!(target_os = "macos") {
+	"osx"
+} else if cfg!(target_pointer_width = "64") {
+	"linux64"
+} else {
+	"linux"
+};
+
+/// Where [`Kinect`](crate::Kinect) looks for backends.
+///
+/// Each entry is either a backend library, which is loaded regardless of its name, or a directory, which is scanned for files named
+/// `rekinect_<name>_<platform>.<ext>` (optionally prefixed with `lib`), where `<platform>` is [`PLATFORM`] and `<ext>` is the platform's
+/// dynamic library extension.
+///
+/// Backends are loaded in search path order. Directories are scanned in descending name order so that e.g. `rekinect_winsdk_v2` is tried
+/// before `rekinect_winsdk_v1`. If two entries provide a backend with the same file name, only the first is loaded.
+#[derive(Clone, Debug, Default)]
+pub struct KinectBackendSearchPath {
+	entries: Vec<PathBuf>,
+}
+impl KinectBackendSearchPath {
+	#[inline]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// [`BACKEND_PATH_ENV`], then [`BACKEND_PATH_CONFIG`], then [`BACKEND_PLUGIN_DIRS`], then the directory of the current executable.
+	pub fn from_env() -> Self {
+		let mut search_path = Self::new();
+
+		if let Some(paths) = std::env::var_os(BACKEND_PATH_ENV) {
+			search_path
+				.entries
+				.extend(std::env::split_paths(&paths).filter(|path| !path.as_os_str().is_empty()));
+		}
+
+		match std::fs::read_to_string(BACKEND_PATH_CONFIG) {
+			Ok(config) => search_path.push_config(&config),
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+			Err(err) => log::warn!("{BACKEND_PATH_CONFIG}: {err:?}"),
+		}
+
+		search_path.entries.extend(BACKEND_PLUGIN_DIRS.iter().map(PathBuf::from));
+
+		if let Some(dir) = std::env::current_exe().ok().as_deref().and_then(Path::parent) {
+			search_path.push(dir);
+		}
+
+		search_path
+	}
+
+	#[inline]
+	pub fn push(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+		self.entries.push(path.into());
+		self
+	}
+
+	/// Appends the paths listed in a [`BACKEND_PATH_CONFIG`]-style file.
+	pub fn push_config(&mut self, config: &str) {
+		self.entries.extend(
+			config
+				.lines()
+				.map(str::trim)
+				.filter(|line| !line.is_empty() && !line.starts_with('#'))
+				.map(PathBuf::from),
+		);
+	}
+
+	#[inline]
+	pub fn entries(&self) -> &[PathBuf] {
+		&self.entries
+	}
+
+	/// Every backend library on the search path, in the order they should be loaded.
+	pub fn discover(&self) -> Vec<PathBuf> {
+		let mut backends = Vec::new();
+
+		let mut add = |path: PathBuf| {
+			if !backends.iter().any(|backend: &PathBuf| backend.file_name() == path.file_name()) {
+				backends.push(path);
+			}
+		};
+
+		for entry in &self.entries {
+			if entry.is_file() {
+				add(entry.clone());
+				continue;
+			}
+
+			let dir = match std::fs::read_dir(entry) {
+				Ok(dir) => dir,
+				Err(err) => {
+					if err.kind() != std::io::ErrorKind::NotFound {
+						log::warn!("{}: {err:?}", entry.display());
+					}
+					continue;
+				}
+			};
+
+			let mut found = dir
+				.filter_map(Result::ok)
+				.map(|entry| entry.path())
+				.filter(|path| path.is_file() && is_backend_file_name(path))
+				.collect::<Vec<_>>();
+
+			found.sort_unstable_by(|a, b| b.file_name().cmp(&a.file_name()));
+			found.into_iter().for_each(&mut add);
+		}
+
+		backends
+	}
+}
+
+/// Whether `path` matches the backend naming scheme, see [`KinectBackendSearchPath`].
+pub fn is_backend_file_name(path: &Path) -> bool {
+	if path.extension().and_then(|ext| ext.to_str()) != Some(std::env::consts::DLL_EXTENSION) {
+		return false;
+	}
+
+	let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+		return false;
+	};
+
+	let stem = stem.strip_prefix("lib").unwrap_or(stem);
+
+	stem.strip_prefix("rekinect_")
+		.and_then(|name| name.strip_suffix(PLATFORM))
+		.and_then(|name| name.strip_suffix('_'))
+		.is_some_and(|name| !name.is_empty())
+}
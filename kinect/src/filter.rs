@@ -0,0 +1,334 @@
+use crate::{
+	math::{self, Vec3},
+	Joint, KinectBody, KinectBodyId, KinectSkeleton, KinectTrackingState,
+};
+use std::{collections::HashMap, time::Duration};
+
+/// Holt double exponential smoothing, as used by the Kinect SDKs' own skeleton smoothing. Works per frame, so it ignores timestamps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectHoltParameters {
+	/// From 0 to 1, how much of the previous estimate to keep. Higher is smoother but lags more.
+	pub smoothing: f32,
+	/// From 0 to 1, how quickly the trend catches up with the raw data. Lower is smoother but slower to correct.
+	pub correction: f32,
+	/// How many frames ahead to predict, to make up for lag.
+	pub prediction: f32,
+	/// Movements smaller than this are treated as jitter and damped, in metres.
+	pub jitter_radius: f32,
+	/// How far the output may stray from the raw data, in metres.
+	pub max_deviation_radius: f32,
+}
+impl Default for KinectHoltParameters {
+	/// The Kinect SDK's defaults.
+	#[inline]
+	fn default() -> Self {
+		Self {
+			smoothing: 0.5,
+			correction: 0.5,
+			prediction: 0.5,
+			jitter_radius: 0.05,
+			max_deviation_radius: 0.04,
+		}
+	}
+}
+
+/// The One Euro filter, a low pass filter whose cutoff rises with speed, so slow movements are smoothed and fast ones stay responsive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectOneEuroParameters {
+	/// The cutoff frequency while still, in Hz. Lower is smoother but lags more.
+	pub min_cutoff: f32,
+	/// How much the cutoff rises per metre per second of speed. Higher lags less when moving quickly.
+	pub beta: f32,
+	/// The cutoff frequency used to smooth the speed, in Hz.
+	pub derivative_cutoff: f32,
+}
+impl Default for KinectOneEuroParameters {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			min_cutoff: 1.0,
+			beta: 0.5,
+			derivative_cutoff: 1.0,
+		}
+	}
+}
+
+/// Smooths a joint's position. Orientations aren't filtered.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum KinectFilter {
+	#[default]
+	None,
+	Holt(KinectHoltParameters),
+	OneEuro(KinectOneEuroParameters),
+}
+
+/// Which [`KinectFilter`] each joint goes through.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KinectFilterConfig {
+	pub joints: [KinectFilter; Joint::COUNT],
+}
+impl KinectFilterConfig {
+	/// Uses `filter` for every joint.
+	#[inline]
+	pub const fn new(filter: KinectFilter) -> Self {
+		Self {
+			joints: [filter; Joint::COUNT],
+		}
+	}
+
+	#[inline]
+	pub const fn with_joint(mut self, joint: Joint, filter: KinectFilter) -> Self {
+		self.joints[joint.sensor_bone()] = filter;
+		self
+	}
+
+	#[inline]
+	pub fn get(&self, joint: Joint) -> KinectFilter {
+		self.joints[joint.sensor_bone()]
+	}
+
+	#[inline]
+	pub fn set(&mut self, joint: Joint, filter: KinectFilter) {
+		self.joints[joint.sensor_bone()] = filter;
+	}
+}
+
+#[derive(Clone, Copy, Default)]
+enum JointFilterState {
+	#[default]
+	None,
+	Holt {
+		raw: Vec3,
+		filtered: Vec3,
+		trend: Vec3,
+		frames: u8,
+	},
+	OneEuro {
+		filtered: Vec3,
+		speed: Vec3,
+		timestamp: Duration,
+	},
+}
+impl JointFilterState {
+	fn apply(&mut self, filter: &KinectFilter, position: Vec3, tracking_state: KinectTrackingState, timestamp: Duration) -> Vec3 {
+		match filter {
+			KinectFilter::None => {
+				*self = Self::None;
+				position
+			}
+
+			KinectFilter::Holt(parameters) => self.holt(parameters, position, tracking_state),
+			KinectFilter::OneEuro(parameters) => self.one_euro(parameters, position, timestamp),
+		}
+	}
+
+	fn holt(&mut self, parameters: &KinectHoltParameters, position: Vec3, tracking_state: KinectTrackingState) -> Vec3 {
+		// Inferred joints are less reliable, so let them wander further before reacting, like the SDK does
+		let leeway = if tracking_state == KinectTrackingState::Inferred { 2.0 } else { 1.0 };
+		let jitter_radius = parameters.jitter_radius * leeway;
+		let max_deviation_radius = parameters.max_deviation_radius * leeway;
+
+		let (raw, filtered, trend, frames) = match *self {
+			Self::Holt {
+				raw: previous_raw,
+				filtered: previous_filtered,
+				trend: previous_trend,
+				frames,
+			} => {
+				let (raw, filtered) = if frames < 2 {
+					(position, math::scale(math::add(position, previous_raw), 0.5))
+				} else {
+					let distance = math::length(math::sub(position, previous_filtered));
+					let raw = if distance <= jitter_radius && jitter_radius > 0.0 {
+						math::lerp(previous_filtered, position, distance / jitter_radius)
+					} else {
+						position
+					};
+
+					(raw, math::lerp(raw, math::add(previous_filtered, previous_trend), parameters.smoothing))
+				};
+
+				let trend = math::lerp(previous_trend, math::sub(filtered, previous_filtered), parameters.correction);
+				(raw, filtered, trend, frames.saturating_add(1))
+			}
+
+			_ => (position, position, [0.0; 3], 1),
+		};
+
+		*self = Self::Holt {
+			raw,
+			filtered,
+			trend,
+			frames,
+		};
+
+		let predicted = math::add(filtered, math::scale(trend, parameters.prediction));
+		let deviation = math::length(math::sub(predicted, raw));
+		if deviation > max_deviation_radius {
+			math::lerp(raw, predicted, max_deviation_radius / deviation)
+		} else {
+			predicted
+		}
+	}
+
+	fn one_euro(&mut self, parameters: &KinectOneEuroParameters, position: Vec3, timestamp: Duration) -> Vec3 {
+		let (filtered, speed) = match *self {
+			Self::OneEuro {
+				filtered: previous_filtered,
+				speed: previous_speed,
+				timestamp: previous_timestamp,
+			} => {
+				// Assume 30 FPS if the backend doesn't have sensible timestamps
+				let dt = match timestamp.checked_sub(previous_timestamp) {
+					Some(dt) if !dt.is_zero() => dt.as_secs_f32(),
+					_ => 1.0 / 30.0,
+				};
+
+				let speed = math::lerp(
+					previous_speed,
+					math::scale(math::sub(position, previous_filtered), 1.0 / dt),
+					smoothing_factor(parameters.derivative_cutoff, dt),
+				);
+
+				let cutoff = parameters.min_cutoff + parameters.beta * math::length(speed);
+				(math::lerp(previous_filtered, position, smoothing_factor(cutoff, dt)), speed)
+			}
+
+			_ => (position, [0.0; 3]),
+		};
+
+		*self = Self::OneEuro { filtered, speed, timestamp };
+		filtered
+	}
+}
+
+/// How far to move towards a new sample for a low pass filter with this cutoff, in Hz.
+#[inline]
+pub(crate) fn smoothing_factor(cutoff: f32, dt: f32) -> f32 {
+	let tau = 1.0 / (2.0 * std::f32::consts::PI * cutoff.max(f32::EPSILON));
+	1.0 / (1.0 + tau / dt)
+}
+
+/// The filter configuration and every body's filter state.
+#[derive(Default)]
+pub(crate) struct KinectFilters {
+	pub(crate) default: KinectFilterConfig,
+	pub(crate) backends: HashMap<String, KinectFilterConfig>,
+	bodies: Vec<(KinectBodyId, Box<[JointFilterState; Joint::COUNT]>)>,
+}
+impl KinectFilters {
+	pub(crate) fn apply(&mut self, backend: &str, body: &mut KinectBody) {
+		let index = self.bodies.iter().position(|(id, _)| *id == body.id);
+
+		if matches!(body.frame.skeleton, KinectSkeleton::Untracked) {
+			if let Some(index) = index {
+				self.bodies.swap_remove(index);
+			}
+			return;
+		}
+
+		let config = self.backends.get(backend).unwrap_or(&self.default);
+
+		let states = match index {
+			Some(index) => &mut self.bodies[index].1,
+			None => {
+				self.bodies.push((body.id, Box::default()));
+				&mut self.bodies.last_mut().unwrap().1
+			}
+		};
+
+		let skeleton = &mut body.frame.skeleton;
+		for joint in Joint::ALL {
+			let state = &mut states[joint.sensor_bone()];
+			let tracking_state = skeleton.tracking_state(joint);
+
+			let Some(bone) = skeleton.bone_mut(joint) else {
+				continue;
+			};
+
+			if tracking_state == KinectTrackingState::NotTracked {
+				*state = JointFilterState::None;
+				continue;
+			}
+
+			*bone = state.apply(&config.joints[joint.sensor_bone()], *bone, tracking_state, body.frame.timestamp);
+		}
+	}
+
+	/// Forgets every body, e.g. when switching backends.
+	#[inline]
+	pub(crate) fn reset(&mut self) {
+		self.bodies.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Runs `positions` through `filter` at 30 FPS.
+	fn run(filter: KinectFilter, positions: impl IntoIterator<Item = Vec3>) -> Vec<Vec3> {
+		let mut state = JointFilterState::default();
+		positions
+			.into_iter()
+			.enumerate()
+			.map(|(i, position)| state.apply(&filter, position, KinectTrackingState::Tracked, Duration::from_secs(i as u64) / 30))
+			.collect()
+	}
+
+	/// Still at the origin, then a step of 0.5m that's held.
+	fn step() -> impl Iterator<Item = Vec3> {
+		std::iter::repeat([0.0; 3]).take(30).chain(std::iter::repeat([0.5, 0.0, 0.0]).take(30))
+	}
+
+	/// How many frames after the step it takes to get within 1cm.
+	fn settling_frames(filtered: &[Vec3]) -> Option<usize> {
+		filtered[30..]
+			.iter()
+			.position(|position| math::length(math::sub(*position, [0.5, 0.0, 0.0])) < 0.01)
+	}
+
+	#[test]
+	fn steady_input_passes_through() {
+		let position = [0.1, 1.2, 2.3];
+		for filter in [
+			KinectFilter::None,
+			KinectFilter::Holt(Default::default()),
+			KinectFilter::OneEuro(Default::default()),
+		] {
+			for filtered in run(filter, std::iter::repeat(position).take(30)) {
+				assert!(math::length(math::sub(filtered, position)) < 1e-5, "{filter:?}: {filtered:?}");
+			}
+		}
+	}
+
+	#[test]
+	fn holt_follows_a_step() {
+		let filtered = run(KinectFilter::Holt(Default::default()), step());
+
+		// Never strays further than max_deviation_radius from the raw data
+		assert!(
+			math::length(math::sub(filtered[30], [0.5, 0.0, 0.0])) <= 0.04 + 1e-5,
+			"{:?}",
+			filtered[30]
+		);
+		assert!(settling_frames(&filtered).is_some_and(|frames| frames <= 10), "{filtered:?}");
+	}
+
+	#[test]
+	fn one_euro_follows_a_step() {
+		let filtered = run(KinectFilter::OneEuro(Default::default()), step());
+
+		// Smoothed, but quicker to catch up the faster it moves
+		assert!(filtered[30][0] > 0.0 && filtered[30][0] < 0.5, "{:?}", filtered[30]);
+		assert!(settling_frames(&filtered).is_some_and(|frames| frames <= 10), "{filtered:?}");
+	}
+
+	#[test]
+	fn one_euro_smooths_jitter() {
+		let jitter = (0..60).map(|i| [if i % 2 == 0 { 0.01 } else { -0.01 }, 0.0, 0.0]);
+		let filtered = run(KinectFilter::OneEuro(Default::default()), jitter);
+
+		assert!(filtered[30..].iter().all(|position| position[0].abs() < 0.005), "{filtered:?}");
+	}
+}
@@ -0,0 +1,126 @@
+use crate::{
+	math::{self, Quat, Vec3},
+	KinectSkeleton,
+};
+
+/// Up, in sensor space.
+pub(crate) const UP: Vec3 = [0.0, 1.0, 0.0];
+/// Away from the sensor, in sensor space.
+const FORWARD: Vec3 = [0.0, 0.0, 1.0];
+/// To the sensor's right, in sensor space.
+const RIGHT: Vec3 = [-1.0, 0.0, 0.0];
+
+/// The floor as estimated by the sensor, in sensor space (see [`KinectAxis`](crate::KinectAxis)).
+///
+/// A point `p` is `normal · p + distance` above the floor, so `distance` is how high the sensor is off the floor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectFloorPlane {
+	/// Normalized, pointing up.
+	pub normal: [f32; 3],
+	pub distance: f32,
+}
+impl KinectFloorPlane {
+	/// Returns `None` if `normal` is zero, which is how the SDKs say they haven't found the floor.
+	#[inline]
+	pub fn new(normal: [f32; 3], distance: f32) -> Option<Self> {
+		let length = math::length(normal);
+		Some(Self {
+			normal: math::normalize(normal)?,
+			distance: distance / length,
+		})
+	}
+
+	/// How far `point` is above the floor.
+	#[inline]
+	pub fn height(&self, point: [f32; 3]) -> f32 {
+		math::dot(self.normal, point) + self.distance
+	}
+}
+
+/// Which way is up from the sensor's point of view.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectSensorOrientation {
+	/// Normalized, in sensor space.
+	pub up: [f32; 3],
+}
+impl KinectSensorOrientation {
+	/// Returns `None` if `up` is zero.
+	#[inline]
+	pub fn new(up: [f32; 3]) -> Option<Self> {
+		Some(Self { up: math::normalize(up)? })
+	}
+
+	/// How far the sensor is tilted upwards, in radians.
+	#[inline]
+	pub fn pitch(&self) -> f32 {
+		// Tilting the sensor up makes up lean towards the sensor
+		(-math::dot(self.up, FORWARD)).atan2(math::dot(self.up, UP))
+	}
+
+	/// How far the sensor is rolled clockwise from its point of view, in radians.
+	#[inline]
+	pub fn roll(&self) -> f32 {
+		math::dot(self.up, RIGHT).atan2(math::dot(self.up, UP))
+	}
+}
+impl From<KinectFloorPlane> for KinectSensorOrientation {
+	#[inline]
+	fn from(floor: KinectFloorPlane) -> Self {
+		Self { up: floor.normal }
+	}
+}
+
+/// What [`Kinect`](crate::Kinect) reports joint positions and orientations relative to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum KinectReferenceFrame {
+	/// As the sensor sees them, so everything tilts with the sensor.
+	#[default]
+	Sensor,
+
+	/// Rotated so that up is straight up, with the floor at zero height directly below the sensor. Applied before the [`KinectTransform`](crate::KinectTransform).
+	///
+	/// Falls back to only correcting the tilt if the sensor can't see the floor, and to [`KinectReferenceFrame::Sensor`] if the backend
+	/// can't tell which way is up.
+	Floor,
+}
+
+/// Takes sensor space to [`KinectReferenceFrame::Floor`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct KinectFloorAlignment {
+	rotation: Quat,
+	offset: Vec3,
+}
+impl KinectFloorAlignment {
+	pub(crate) fn new(floor: Option<KinectFloorPlane>, orientation: Option<KinectSensorOrientation>) -> Option<Self> {
+		let up = floor.map(KinectSensorOrientation::from).or(orientation)?.up;
+
+		Some(Self {
+			rotation: math::quat_from_to(up, UP),
+			offset: floor.map(|floor| math::scale(UP, floor.distance)).unwrap_or_default(),
+		})
+	}
+
+	pub(crate) fn apply(&self, skeleton: &mut KinectSkeleton) {
+		if let Some(skeleton) = skeleton.tracked_mut() {
+			self.apply_bones(skeleton.raw_bones_mut());
+			self.apply_orientations(skeleton.orientations_mut().map_or(&mut [], |orientations| &mut orientations[..]));
+		}
+
+		if let Some(extended) = skeleton.extended_mut() {
+			self.apply_bones(extended.raw_bones_mut());
+			self.apply_orientations(extended.orientations_mut().map_or(&mut [], |orientations| &mut orientations[..]));
+		}
+	}
+
+	fn apply_bones(&self, bones: &mut [[f32; 3]]) {
+		for bone in bones {
+			*bone = math::add(math::quat_rotate(self.rotation, *bone), self.offset);
+		}
+	}
+
+	fn apply_orientations(&self, orientations: &mut [[f32; 4]]) {
+		for orientation in orientations {
+			*orientation = math::quat_mul(self.rotation, *orientation);
+		}
+	}
+}
@@ -0,0 +1,574 @@
+use crate::{
+	math::{self, Vec3},
+	Joint, KinectBody, KinectBodyId, KinectSkeleton,
+};
+use std::{collections::VecDeque, path::Path, time::Duration};
+
+/// Where Garry's Mod keeps recorded [`KinectGestureTemplate`]s, one `<name>.txt` per gesture.
+pub const GESTURE_TEMPLATE_DIRECTORY: &str = "garrysmod/data/rekinect/gestures";
+
+/// How many frames a template needs at least.
+const MIN_TEMPLATE_FRAMES: usize = 5;
+
+/// How much slower or faster than the template a gesture may be performed.
+const MAX_STRETCH: usize = 2;
+
+/// How closely motion has to follow a [`KinectGestureTemplate`] for [`Kinect`](crate::Kinect) to recognise it.
+///
+/// Distances are in shoulder widths, so they don't depend on how big the player is or the [`KinectTransform`](crate::KinectTransform)'s
+/// scale.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectGestureRecognition {
+	/// The average distance between the template's joints and the body's at which confidence drops to zero.
+	pub max_distance: f32,
+	/// From 0 to 1, how confident a match has to be to raise a [`KinectGestureEvent`].
+	pub min_confidence: f32,
+}
+impl Default for KinectGestureRecognition {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			max_distance: 0.5,
+			min_confidence: 0.6,
+		}
+	}
+}
+impl KinectGestureRecognition {
+	#[inline]
+	pub const fn with_max_distance(mut self, max_distance: f32) -> Self {
+		self.max_distance = max_distance;
+		self
+	}
+
+	#[inline]
+	pub const fn with_min_confidence(mut self, min_confidence: f32) -> Self {
+		self.min_confidence = min_confidence;
+		self
+	}
+}
+
+/// A body performed a gesture, see [`Kinect::take_gestures`](crate::Kinect::take_gestures).
+#[derive(Clone, Debug, PartialEq)]
+pub struct KinectGestureEvent {
+	pub body: KinectBodyId,
+	/// The [`KinectGestureTemplate::name`] that matched.
+	pub name: String,
+	/// From [`KinectGestureRecognition::min_confidence`] to 1.
+	pub confidence: f32,
+	/// The [`KinectFrame::timestamp`](crate::KinectFrame::timestamp) of the frame that matched best. The event is raised up to half the template's length later, in
+	/// case the motion goes on to match better.
+	pub timestamp: Duration,
+}
+
+/// A recorded example of a gesture, matched against incoming motion with dynamic time warping so it can be performed at a different speed.
+///
+/// Only the joints the gesture is made with are recorded, relative to the shoulders and in shoulder widths. The player should face the
+/// sensor, both while recording and while performing it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KinectGestureTemplate {
+	pub name: String,
+	pub joints: Vec<Joint>,
+	/// One position per joint in [`joints`](Self::joints) for every recorded frame.
+	pub frames: Vec<Vec<[f32; 3]>>,
+}
+impl KinectGestureTemplate {
+	/// Records a template from a stream of skeletons, e.g. from a [`KinectSubscription`](crate::KinectSubscription) or a replay.
+	///
+	/// Returns `None` if too few of the skeletons were tracked.
+	pub fn from_skeletons<'a>(
+		name: impl Into<String>,
+		joints: impl Into<Vec<Joint>>,
+		skeletons: impl IntoIterator<Item = &'a KinectSkeleton>,
+	) -> Option<Self> {
+		let mut recorder = KinectGestureRecorder::new(joints);
+		for skeleton in skeletons {
+			recorder.push(skeleton);
+		}
+		recorder.finish(name)
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+		std::fs::read_to_string(path)?.parse()
+	}
+
+	/// Loads every `.txt` template in `dir`, logging any that can't be read.
+	pub fn load_dir(dir: impl AsRef<Path>) -> Result<Vec<Self>, std::io::Error> {
+		let mut templates = Vec::new();
+
+		for entry in std::fs::read_dir(dir)? {
+			let path = entry?.path();
+			if path.extension().and_then(|extension| extension.to_str()) != Some("txt") {
+				continue;
+			}
+
+			match Self::load(&path) {
+				Ok(template) => templates.push(template),
+				Err(err) => log::warn!("{}: {err:?}", path.display()),
+			}
+		}
+
+		Ok(templates)
+	}
+
+	/// Creates the parent directory if needed.
+	pub fn save(&self, path: impl AsRef<Path>) -> Result<(), std::io::Error> {
+		let path = path.as_ref();
+		if let Some(dir) = path.parent() {
+			std::fs::create_dir_all(dir)?;
+		}
+		std::fs::write(path, self.to_string())
+	}
+
+	/// How far the body's joints are from this frame's, on average.
+	fn distance(&self, frame: &[Vec3], features: &Features) -> f32 {
+		let mut total = 0.0;
+
+		for (joint, position) in self.joints.iter().zip(frame) {
+			match features[joint.sensor_bone()] {
+				Some(feature) => total += math::length(math::sub(*position, feature)),
+				None => return f32::INFINITY,
+			}
+		}
+
+		total / self.joints.len() as f32
+	}
+
+	/// Aligns the template's end with the latest frame in `history`, letting its start land anywhere within [`MAX_STRETCH`], and returns the
+	/// average distance along the best alignment, or the distance between the two latest frames if that's further. Otherwise a motion
+	/// that's most of the way through would match nearly as well as a finished one.
+	fn best_match(&self, history: &VecDeque<Features>) -> Option<f32> {
+		let n = self.frames.len();
+		let m = history.len().min(n * MAX_STRETCH);
+		if n == 0 || m < n.div_ceil(MAX_STRETCH) {
+			return None;
+		}
+
+		// Both sequences are walked backwards from the latest frame, so every alignment ends there
+		let template = |i: usize| &self.frames[n - 1 - i];
+		let body = |j: usize| &history[history.len() - 1 - j];
+
+		// The total cost of the cheapest path to each cell
+		let mut previous = vec![f32::INFINITY; m];
+		let mut current = vec![f32::INFINITY; m];
+
+		for i in 0..n {
+			for j in 0..m {
+				let best = if i == 0 && j == 0 {
+					0.0
+				} else {
+					[
+						(i > 0).then(|| previous[j]),
+						(j > 0).then(|| current[j - 1]),
+						(i > 0 && j > 0).then(|| previous[j - 1]),
+					]
+					.into_iter()
+					.flatten()
+					.fold(f32::INFINITY, f32::min)
+				};
+
+				current[j] = best + self.distance(template(i), body(j));
+			}
+
+			std::mem::swap(&mut previous, &mut current);
+		}
+
+		// Divided by the shortest possible path's length, so warping more than needed costs more
+		let average = (n.div_ceil(MAX_STRETCH) - 1..m)
+			.map(|j| previous[j] / n.max(j + 1) as f32)
+			.filter(|distance| distance.is_finite())
+			.min_by(f32::total_cmp)?;
+
+		Some(average.max(self.distance(template(0), body(0))))
+	}
+}
+impl std::fmt::Display for KinectGestureTemplate {
+	/// One `key value...` per line, which [`str::parse`] reads back.
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(
+			f,
+			"# gmcl_rekinect gesture template, positions are in shoulder widths from the shoulder centre"
+		)?;
+		writeln!(f, "name {}", self.name)?;
+
+		write!(f, "joints")?;
+		for joint in &self.joints {
+			write!(f, " {}", joint.name())?;
+		}
+		writeln!(f)?;
+
+		for frame in &self.frames {
+			write!(f, "frame")?;
+			for position in frame {
+				write!(f, " {} {} {}", position[0], position[1], position[2])?;
+			}
+			writeln!(f)?;
+		}
+
+		Ok(())
+	}
+}
+impl std::str::FromStr for KinectGestureTemplate {
+	type Err = std::io::Error;
+
+	/// Blank lines, lines starting with `#` and unknown keys are ignored. `joints` has to come before any `frame`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = |line: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Invalid gesture template line: {line:?}"));
+
+		let mut template = Self {
+			name: String::new(),
+			joints: Vec::new(),
+			frames: Vec::new(),
+		};
+
+		for line in s.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+			let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+			match key {
+				"name" => template.name = value.trim().to_string(),
+
+				"joints" => {
+					template.joints = value
+						.split_whitespace()
+						.map(|name| Joint::from_name(name).ok_or_else(|| invalid(line)))
+						.collect::<Result<_, _>>()?;
+				}
+
+				"frame" => {
+					let numbers = value
+						.split_whitespace()
+						.map(|number| {
+							number
+								.parse::<f32>()
+								.ok()
+								.filter(|number| number.is_finite())
+								.ok_or_else(|| invalid(line))
+						})
+						.collect::<Result<Vec<_>, _>>()?;
+
+					if numbers.len() != template.joints.len() * 3 {
+						return Err(invalid(line));
+					}
+
+					template
+						.frames
+						.push(numbers.chunks_exact(3).map(|xyz| [xyz[0], xyz[1], xyz[2]]).collect());
+				}
+
+				_ => {}
+			}
+		}
+
+		if template.name.is_empty() || template.joints.is_empty() || template.frames.len() < MIN_TEMPLATE_FRAMES {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("Gesture template needs a name, joints and at least {MIN_TEMPLATE_FRAMES} frames"),
+			));
+		}
+
+		Ok(template)
+	}
+}
+
+/// Every joint relative to the shoulder centre, in shoulder widths. `None` for joints the skeleton doesn't have.
+type Features = [Option<Vec3>; Joint::COUNT];
+
+fn features(skeleton: &KinectSkeleton) -> Option<Features> {
+	let (&center, &left, &right) = (
+		skeleton.bone(Joint::ShoulderCenter)?,
+		skeleton.bone(Joint::ShoulderLeft)?,
+		skeleton.bone(Joint::ShoulderRight)?,
+	);
+
+	let shoulder_width = math::length(math::sub(left, right));
+	if shoulder_width <= f32::EPSILON || !shoulder_width.is_finite() {
+		return None;
+	}
+
+	Some(Joint::ALL.map(|joint| Some(math::scale(math::sub(*skeleton.bone(joint)?, center), 1.0 / shoulder_width))))
+}
+
+/// Builds a [`KinectGestureTemplate`] one skeleton at a time.
+#[derive(Clone, Debug)]
+pub struct KinectGestureRecorder {
+	joints: Vec<Joint>,
+	frames: Vec<Vec<[f32; 3]>>,
+}
+impl KinectGestureRecorder {
+	#[inline]
+	pub fn new(joints: impl Into<Vec<Joint>>) -> Self {
+		Self {
+			joints: joints.into(),
+			frames: Vec::new(),
+		}
+	}
+
+	#[inline]
+	pub fn joints(&self) -> &[Joint] {
+		&self.joints
+	}
+
+	/// How many frames have been recorded so far.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.frames.len()
+	}
+
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.frames.is_empty()
+	}
+
+	/// Skeletons that aren't tracked or are missing any of the joints are skipped. Returns whether this one was recorded.
+	pub fn push(&mut self, skeleton: &KinectSkeleton) -> bool {
+		let Some(features) = features(skeleton) else {
+			return false;
+		};
+
+		let Some(frame) = self.joints.iter().map(|joint| features[joint.sensor_bone()]).collect() else {
+			return false;
+		};
+
+		self.frames.push(frame);
+		true
+	}
+
+	/// Returns `None` if there are no joints, or fewer than 5 frames were recorded.
+	pub fn finish(self, name: impl Into<String>) -> Option<KinectGestureTemplate> {
+		if self.joints.is_empty() || self.frames.len() < MIN_TEMPLATE_FRAMES {
+			return None;
+		}
+
+		Some(KinectGestureTemplate {
+			name: name.into(),
+			joints: self.joints,
+			frames: self.frames,
+		})
+	}
+}
+
+/// The best match so far.
+struct Candidate {
+	event: KinectGestureEvent,
+	/// How many more frames to wait for a better match before raising it.
+	patience: usize,
+	/// How many frames came after it.
+	age: usize,
+}
+
+/// Recent motion, for matching against templates.
+#[derive(Default)]
+struct BodyGestures {
+	history: VecDeque<Features>,
+	candidate: Option<Candidate>,
+}
+
+/// The gesture templates, the selected body's recording and every body's recent motion.
+#[derive(Default)]
+pub(crate) struct KinectGestures {
+	pub(crate) recognition: KinectGestureRecognition,
+	pub(crate) templates: Vec<KinectGestureTemplate>,
+	pub(crate) recorder: Option<KinectGestureRecorder>,
+	pub(crate) events: Vec<KinectGestureEvent>,
+	bodies: Vec<(KinectBodyId, Box<BodyGestures>)>,
+}
+impl KinectGestures {
+	/// Events nobody takes are dropped after this many, oldest first.
+	const MAX_EVENTS: usize = 64;
+
+	pub(crate) fn apply(&mut self, body: &KinectBody, selected: bool) {
+		let index = self.bodies.iter().position(|(id, _)| *id == body.id);
+
+		if matches!(body.frame.skeleton, KinectSkeleton::Untracked) {
+			if let Some(index) = index {
+				self.bodies.swap_remove(index);
+			}
+			return;
+		}
+
+		if selected {
+			if let Some(recorder) = &mut self.recorder {
+				recorder.push(&body.frame.skeleton);
+			}
+		}
+
+		if self.templates.is_empty() {
+			return;
+		}
+
+		let Some(features) = features(&body.frame.skeleton) else {
+			return;
+		};
+
+		let state = match index {
+			Some(index) => &mut self.bodies[index].1,
+			None => {
+				self.bodies.push((body.id, Box::default()));
+				&mut self.bodies.last_mut().unwrap().1
+			}
+		};
+
+		let capacity = self
+			.templates
+			.iter()
+			.map(|template| template.frames.len() * MAX_STRETCH)
+			.max()
+			.unwrap_or_default();
+		state.history.push_back(features);
+		while state.history.len() > capacity {
+			state.history.pop_front();
+		}
+
+		let best = self
+			.templates
+			.iter()
+			.filter_map(|template| {
+				let distance = template.best_match(&state.history)?;
+				Some((template, 1.0 - distance / self.recognition.max_distance.max(f32::EPSILON)))
+			})
+			.max_by(|a, b| a.1.total_cmp(&b.1));
+
+		let best = best.filter(|(_, confidence)| *confidence >= self.recognition.min_confidence);
+
+		match (best, &mut state.candidate) {
+			(Some((template, confidence)), candidate) if candidate.as_ref().map_or(true, |candidate| confidence >= candidate.event.confidence) => {
+				state.candidate = Some(Candidate {
+					event: KinectGestureEvent {
+						body: body.id,
+						name: template.name.clone(),
+						confidence: confidence.min(1.0),
+						timestamp: body.frame.timestamp,
+					},
+					patience: template.frames.len() / 2,
+					age: 0,
+				});
+				return;
+			}
+
+			// Partway through, a motion can briefly match a squashed version of the template. Give it a chance to finish
+			(_, Some(candidate)) if candidate.patience > 0 => {
+				candidate.patience -= 1;
+				candidate.age += 1;
+				return;
+			}
+
+			_ => {}
+		}
+
+		let Some(candidate) = state.candidate.take() else {
+			return;
+		};
+
+		// Each motion only counts once, but whatever came after it may be the start of the next one
+		let after = (candidate.age + 1).min(state.history.len());
+		state.history.drain(..state.history.len() - after);
+
+		self.push(candidate.event);
+	}
+
+	/// Also used for sequences from [`KinectRules`](crate::KinectRules).
+	pub(crate) fn push(&mut self, event: KinectGestureEvent) {
+		if self.events.len() >= Self::MAX_EVENTS {
+			self.events.remove(0);
+		}
+		self.events.push(event);
+	}
+
+	/// Forgets every body's motion, e.g. when switching backends.
+	#[inline]
+	pub(crate) fn reset(&mut self) {
+		self.bodies.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{testing, KinectTrackingState};
+
+	/// Facing the sensor with the right hand at `hand`, relative to the shoulder centre.
+	fn skeleton(hand: Vec3) -> KinectSkeleton {
+		let center = [0.0, 1.4, 2.0];
+		testing::skeleton(
+			&[
+				(Joint::ShoulderCenter, center),
+				(Joint::ShoulderLeft, math::add(center, [0.2, 0.0, 0.0])),
+				(Joint::ShoulderRight, math::add(center, [-0.2, 0.0, 0.0])),
+				(Joint::HandRight, math::add(center, hand)),
+			],
+			KinectTrackingState::Tracked,
+		)
+	}
+
+	/// One side to side wave of the right hand.
+	fn wave() -> Vec<KinectSkeleton> {
+		(0..20)
+			.map(|i| skeleton([-0.3 + 0.2 * (i as f32 * std::f32::consts::TAU / 20.0).sin(), 0.3, 0.0]))
+			.collect()
+	}
+
+	fn template() -> KinectGestureTemplate {
+		KinectGestureTemplate::from_skeletons("wave", [Joint::HandRight], &wave()).unwrap()
+	}
+
+	fn recognize(template: KinectGestureTemplate, skeletons: impl IntoIterator<Item = KinectSkeleton>) -> Vec<KinectGestureEvent> {
+		let mut gestures = KinectGestures {
+			templates: vec![template],
+			..Default::default()
+		};
+
+		for (skeleton, sequence) in skeletons.into_iter().zip(0..) {
+			gestures.apply(&testing::body(skeleton, sequence), true);
+		}
+
+		gestures.events
+	}
+
+	#[test]
+	fn template_round_trip() {
+		let template = template();
+		assert_eq!(template.to_string().parse::<KinectGestureTemplate>().unwrap(), template);
+
+		let path = std::env::temp_dir().join(format!("rekinect_gesture_test_{}.txt", std::process::id()));
+		template.save(&path).unwrap();
+		let loaded = KinectGestureTemplate::load(&path);
+		std::fs::remove_file(&path).ok();
+		assert_eq!(loaded.unwrap(), template);
+	}
+
+	#[test]
+	fn template_rejects_bad_lines() {
+		assert!("name wave\njoints NOT_A_JOINT\n".parse::<KinectGestureTemplate>().is_err());
+		assert!("name wave\njoints HAND_RIGHT\nframe 1 2\n".parse::<KinectGestureTemplate>().is_err());
+		assert!("name wave\njoints HAND_RIGHT\nframe 1 2 3\n".parse::<KinectGestureTemplate>().is_err());
+	}
+
+	#[test]
+	fn replaying_a_template_raises_it_once() {
+		let rest = skeleton([-0.3, -0.5, 0.0]);
+		// Then rest for long enough that it's raised
+		let skeletons = std::iter::repeat(rest).take(10).chain(wave()).chain(std::iter::repeat(rest).take(20));
+
+		let events = recognize(template(), skeletons);
+		assert_eq!(events.len(), 1, "{events:?}");
+		assert_eq!(events[0].name, "wave");
+		assert!(events[0].confidence > 0.95, "{events:?}");
+	}
+
+	#[test]
+	fn repeating_a_template_raises_it_each_time() {
+		let rest = skeleton([-0.3, -0.5, 0.0]);
+		let skeletons = std::iter::repeat(wave()).take(3).flatten().chain(std::iter::repeat(rest).take(20));
+
+		let events = recognize(template(), skeletons);
+		assert_eq!(events.len(), 3, "{events:?}");
+	}
+
+	#[test]
+	fn unrelated_motion_raises_nothing() {
+		// Raising and lowering the left side of the body's hand, nowhere near the wave
+		let skeletons = (0..60).map(|i| skeleton([0.3, -0.5 + 0.4 * (i as f32 * std::f32::consts::TAU / 20.0).sin(), -0.3]));
+
+		let events = recognize(template(), skeletons);
+		assert!(events.is_empty(), "{events:?}");
+	}
+}
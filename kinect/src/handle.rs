@@ -0,0 +1,235 @@
+use crate::{
+	abi::HostWaker, Kinect, KinectBodyId, KinectBodyMetrics, KinectBodyProfile, KinectCalibrationEvent, KinectCalibrationState, KinectFloorPlane,
+	KinectFrame, KinectGestureEvent, KinectInterpolation, KinectInterpolator, KinectPoseEvent, KinectSensorOrientation, KinectSkeleton,
+};
+use std::{
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex, PoisonError, RwLock,
+	},
+	thread::JoinHandle,
+	time::{Duration, Instant},
+};
+
+/// What a [`KinectHandle`] last saw.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KinectSnapshot {
+	/// The selected body's latest frame. [`KinectSkeleton::Untracked`](crate::KinectSkeleton::Untracked) if nobody is being tracked.
+	pub frame: KinectFrame,
+	/// The selected body, see [`Kinect::selected_body`].
+	pub body: Option<KinectBodyId>,
+	/// See [`Kinect::available`].
+	pub available: bool,
+	/// See [`Kinect::floor_plane`].
+	pub floor_plane: Option<KinectFloorPlane>,
+	/// See [`Kinect::sensor_orientation`].
+	pub sensor_orientation: Option<KinectSensorOrientation>,
+	/// See [`Kinect::calibration_state`].
+	pub calibration: KinectCalibrationState,
+	/// See [`Kinect::profile`].
+	pub profile: Option<KinectBodyProfile>,
+	/// The selected body's, see [`Kinect::body_metrics`].
+	pub metrics: Option<KinectBodyMetrics>,
+}
+
+type KinectCommand = Box<dyn FnOnce(&mut Kinect) + Send>;
+
+struct KinectShared {
+	snapshot: RwLock<KinectSnapshot>,
+	interpolator: RwLock<KinectInterpolator>,
+	commands: Mutex<Vec<KinectCommand>>,
+	calibrations: Mutex<Vec<KinectCalibrationEvent>>,
+	gestures: Mutex<Vec<KinectGestureEvent>>,
+	poses: Mutex<Vec<KinectPoseEvent>>,
+	shutdown: AtomicBool,
+	waker: Arc<HostWaker>,
+}
+
+/// Owns a [`Kinect`] on a background thread which polls it as soon as a backend has something new, and publishes a [`KinectSnapshot`]
+/// that any thread can read.
+///
+/// The [`Kinect`] never leaves its thread, so backends are created, polled and destroyed on the same thread.
+pub struct KinectHandle {
+	shared: Arc<KinectShared>,
+	thread: Option<JoinHandle<()>>,
+}
+impl KinectHandle {
+	/// How long the polling thread sleeps for at most when no backend wakes it, so that backends still get re-probed.
+	pub const POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+	/// Events nobody takes are dropped after this many, oldest first.
+	pub const MAX_EVENTS: usize = 64;
+
+	/// Spawns a polling thread for [`Kinect::new`].
+	#[inline]
+	pub fn new() -> Result<Self, std::io::Error> {
+		Self::spawn(Kinect::new)
+	}
+
+	/// Spawns a polling thread for the [`Kinect`] returned by `init`, which is called on that thread.
+	pub fn spawn(init: impl FnOnce() -> Result<Kinect, std::io::Error> + Send + 'static) -> Result<Self, std::io::Error> {
+		let (tx, rx) = std::sync::mpsc::sync_channel(1);
+
+		let thread = std::thread::Builder::new().name("gmcl_rekinect".to_string()).spawn(move || {
+			let mut kinect = match init() {
+				Ok(kinect) => kinect,
+				Err(err) => {
+					tx.send(Err(err)).ok();
+					return;
+				}
+			};
+
+			let shared = Arc::new(KinectShared {
+				snapshot: RwLock::new(KinectSnapshot {
+					available: kinect.available(),
+					..Default::default()
+				}),
+				interpolator: RwLock::new(KinectInterpolator::default()),
+				commands: Mutex::new(Vec::new()),
+				calibrations: Mutex::new(Vec::new()),
+				gestures: Mutex::new(Vec::new()),
+				poses: Mutex::new(Vec::new()),
+				shutdown: AtomicBool::new(false),
+				waker: kinect.waker.clone(),
+			});
+
+			if tx.send(Ok(shared.clone())).is_err() {
+				return;
+			}
+
+			Self::run(&mut kinect, &shared);
+		})?;
+
+		match rx.recv() {
+			Ok(Ok(shared)) => Ok(Self {
+				shared,
+				thread: Some(thread),
+			}),
+
+			Ok(Err(err)) => Err(err),
+
+			Err(_) => Err(std::io::Error::new(std::io::ErrorKind::Other, "Kinect thread panicked during startup")),
+		}
+	}
+
+	fn run(kinect: &mut Kinect, shared: &KinectShared) {
+		while !shared.shutdown.load(Ordering::Acquire) {
+			let commands = std::mem::take(&mut *shared.commands.lock().unwrap_or_else(PoisonError::into_inner));
+			for command in commands {
+				command(kinect);
+			}
+
+			let frame = kinect.poll();
+			let received = Instant::now();
+			let (available, floor_plane, sensor_orientation) = (kinect.available(), kinect.floor_plane(), kinect.sensor_orientation());
+
+			let mut snapshot = shared.snapshot.write().unwrap_or_else(PoisonError::into_inner);
+			if let Some(frame) = frame {
+				snapshot.frame = frame;
+			}
+			snapshot.body = kinect.selected_body();
+			snapshot.available = available;
+			snapshot.floor_plane = floor_plane;
+			snapshot.sensor_orientation = sensor_orientation;
+			snapshot.calibration = kinect.calibration_state();
+			snapshot.profile = kinect.profile().copied();
+			snapshot.metrics = kinect.selected_body().and_then(|body| kinect.body_metrics(body));
+			drop(snapshot);
+
+			if let Some(frame) = frame {
+				let mut interpolator = shared.interpolator.write().unwrap_or_else(PoisonError::into_inner);
+				interpolator.push(kinect.selected_body(), frame, received);
+			}
+
+			Self::queue(&shared.calibrations, kinect.take_calibrations());
+			Self::queue(&shared.gestures, kinect.take_gestures());
+			Self::queue(&shared.poses, kinect.take_poses());
+
+			kinect.wait(Some(Self::POLL_TIMEOUT));
+		}
+	}
+
+	fn queue<T>(queue: &Mutex<Vec<T>>, events: Vec<T>) {
+		if events.is_empty() {
+			return;
+		}
+
+		let mut queue = queue.lock().unwrap_or_else(PoisonError::into_inner);
+		queue.extend(events);
+		let overflow = queue.len().saturating_sub(Self::MAX_EVENTS);
+		queue.drain(..overflow);
+	}
+
+	/// The latest state published by the polling thread.
+	#[inline]
+	pub fn snapshot(&self) -> KinectSnapshot {
+		*self.shared.snapshot.read().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// The selected body's skeleton at `at`, interpolated between or extrapolated from its latest frames, see [`KinectInterpolator`].
+	///
+	/// Smoother than [`KinectSnapshot::frame`] when sampled more often than the sensor's 30 FPS, and can be sampled for any moment,
+	/// e.g. when a tick happens rather than when it's processed.
+	#[inline]
+	pub fn sample(&self, at: Instant) -> KinectSkeleton {
+		self.shared.interpolator.read().unwrap_or_else(PoisonError::into_inner).sample(at)
+	}
+
+	#[inline]
+	pub fn interpolation(&self) -> KinectInterpolation {
+		*self.shared.interpolator.read().unwrap_or_else(PoisonError::into_inner).interpolation()
+	}
+
+	#[inline]
+	pub fn set_interpolation(&self, interpolation: KinectInterpolation) {
+		self.shared
+			.interpolator
+			.write()
+			.unwrap_or_else(PoisonError::into_inner)
+			.set_interpolation(interpolation);
+	}
+
+	/// Every calibration that finished since the last call, oldest first, see [`Kinect::take_calibrations`].
+	#[inline]
+	pub fn take_calibrations(&self) -> Vec<KinectCalibrationEvent> {
+		std::mem::take(&mut *self.shared.calibrations.lock().unwrap_or_else(PoisonError::into_inner))
+	}
+
+	/// Every gesture recognised since the last call, oldest first, see [`Kinect::take_gestures`].
+	#[inline]
+	pub fn take_gestures(&self) -> Vec<KinectGestureEvent> {
+		std::mem::take(&mut *self.shared.gestures.lock().unwrap_or_else(PoisonError::into_inner))
+	}
+
+	/// Every pose struck or released since the last call, oldest first, see [`Kinect::take_poses`].
+	#[inline]
+	pub fn take_poses(&self) -> Vec<KinectPoseEvent> {
+		std::mem::take(&mut *self.shared.poses.lock().unwrap_or_else(PoisonError::into_inner))
+	}
+
+	/// Runs `command` on the polling thread before its next poll, e.g. to subscribe or change the policy.
+	pub fn run_on_thread(&self, command: impl FnOnce(&mut Kinect) + Send + 'static) {
+		self.shared
+			.commands
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.push(Box::new(command));
+
+		self.shared.waker.wake();
+	}
+}
+impl Drop for KinectHandle {
+	fn drop(&mut self) {
+		self.shared.shutdown.store(true, Ordering::Release);
+		self.shared.waker.wake();
+
+		if let Some(thread) = self.thread.take() {
+			thread.join().ok();
+		}
+	}
+}
+impl std::fmt::Debug for KinectHandle {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("KinectHandle").field("snapshot", &self.snapshot()).finish()
+	}
+}
@@ -0,0 +1,197 @@
+use crate::{
+	math::{self, Vec3},
+	Joint, KinectBody, KinectBodyId, KinectSkeleton, KinectTrackingState,
+};
+use std::time::Duration;
+
+/// How [`Kinect`](crate::Kinect) fills in joints the sensor lost, e.g. a forearm behind the torso.
+///
+/// Elbows and knees are placed with two-bone IK between a measured shoulder or hip and a measured wrist or ankle, bending the same way they
+/// did in the last good pose. Anything else hangs off its [`Joint::parent`] in the direction it last did. Bone lengths come from
+/// [`KinectValidation`](crate::KinectValidation) once it has learned them, or from the last good pose otherwise.
+///
+/// Filled in joints are reported as [`KinectTrackingState::Synthesized`]. Off by default, see [`Kinect::set_inference`](crate::Kinect::set_inference).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectInference {
+	/// Also replace [`KinectTrackingState::Inferred`] joints, which are the sensor's own guesses, not just [`KinectTrackingState::NotTracked`] ones.
+	pub replace_inferred: bool,
+	/// Joints that haven't been measured for this long are left alone, as the last good pose is too stale to go on.
+	pub max_age: Duration,
+}
+impl Default for KinectInference {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			replace_inferred: false,
+			max_age: Duration::from_secs(2),
+		}
+	}
+}
+impl KinectInference {
+	#[inline]
+	pub const fn with_replace_inferred(mut self, replace_inferred: bool) -> Self {
+		self.replace_inferred = replace_inferred;
+		self
+	}
+
+	#[inline]
+	pub const fn with_max_age(mut self, max_age: Duration) -> Self {
+		self.max_age = max_age;
+		self
+	}
+
+	#[inline]
+	fn needs_replacing(&self, tracking_state: KinectTrackingState) -> bool {
+		match tracking_state {
+			KinectTrackingState::NotTracked | KinectTrackingState::Synthesized => true,
+			KinectTrackingState::Inferred => self.replace_inferred,
+			KinectTrackingState::Tracked => false,
+		}
+	}
+}
+
+/// A joint as it was the last time it and its parent were both measured.
+#[derive(Clone, Copy)]
+struct GoodJoint {
+	/// From the parent to the joint.
+	offset: Vec3,
+	timestamp: Duration,
+}
+
+struct BodyInference {
+	good: [Option<GoodJoint>; Joint::COUNT],
+}
+impl Default for BodyInference {
+	#[inline]
+	fn default() -> Self {
+		Self { good: [None; Joint::COUNT] }
+	}
+}
+impl BodyInference {
+	fn apply(&mut self, inference: &KinectInference, skeleton: &mut KinectSkeleton, timestamp: Duration, learned: impl Fn(Joint) -> Option<f32>) {
+		let fresh = |good: &Option<GoodJoint>| good.filter(|good| timestamp.saturating_sub(good.timestamp) <= inference.max_age);
+		let length = |joint: Joint, good: &GoodJoint| learned(joint).unwrap_or_else(|| math::length(good.offset));
+
+		for joint in Joint::ALL {
+			let Some(parent) = joint.parent() else {
+				continue;
+			};
+
+			let (Some(&position), Some(&parent_position)) = (skeleton.bone(joint), skeleton.bone(parent)) else {
+				continue;
+			};
+
+			let tracking_state = skeleton.tracking_state(joint);
+			let parent_tracking_state = skeleton.tracking_state(parent);
+			let index = joint.sensor_bone();
+
+			if !inference.needs_replacing(tracking_state) {
+				if tracking_state == KinectTrackingState::Tracked && parent_tracking_state == KinectTrackingState::Tracked {
+					self.good[index] = Some(GoodJoint {
+						offset: math::sub(position, parent_position),
+						timestamp,
+					});
+				}
+				continue;
+			}
+
+			// Nothing to hang it off
+			if parent_tracking_state == KinectTrackingState::NotTracked {
+				continue;
+			}
+
+			let Some(good) = fresh(&self.good[index]) else {
+				continue;
+			};
+
+			let synthesized = self.solve_two_bone(skeleton, joint, parent_position, &length, fresh).or_else(|| {
+				Some(math::add(
+					parent_position,
+					math::scale(math::normalize(good.offset)?, length(joint, &good)),
+				))
+			});
+
+			if let Some(synthesized) = synthesized {
+				if let Some(bone) = skeleton.bone_mut(joint) {
+					*bone = synthesized;
+				}
+				if let Some(state) = skeleton.tracking_state_mut(joint) {
+					*state = KinectTrackingState::Synthesized;
+				}
+			}
+		}
+	}
+
+	/// Places an elbow or knee between its parent and a measured child, so the limb still reaches where the sensor saw its end.
+	fn solve_two_bone(
+		&self,
+		skeleton: &KinectSkeleton,
+		joint: Joint,
+		root: Vec3,
+		length: &impl Fn(Joint, &GoodJoint) -> f32,
+		fresh: impl Fn(&Option<GoodJoint>) -> Option<GoodJoint>,
+	) -> Option<Vec3> {
+		let end = match joint {
+			Joint::ElbowLeft => Joint::WristLeft,
+			Joint::ElbowRight => Joint::WristRight,
+			Joint::KneeLeft => Joint::AnkleLeft,
+			Joint::KneeRight => Joint::AnkleRight,
+			_ => return None,
+		};
+
+		if skeleton.tracking_state(end) != KinectTrackingState::Tracked {
+			return None;
+		}
+
+		let (upper, lower) = (fresh(&self.good[joint.sensor_bone()])?, fresh(&self.good[end.sensor_bone()])?);
+		let (upper_length, lower_length) = (length(joint, &upper), length(end, &lower));
+
+		let reach = math::sub(*skeleton.bone(end)?, root);
+		let direction = math::normalize(reach)?;
+		let distance = math::length(reach).clamp((upper_length - lower_length).abs() + 1e-4, upper_length + lower_length - 1e-4);
+
+		// Bend the same way as in the last good pose
+		let bend = math::normalize(math::sub(upper.offset, math::scale(direction, math::dot(upper.offset, direction))))
+			.or_else(|| math::normalize(math::cross(direction, [0.0, 1.0, 0.0])))
+			.or_else(|| math::normalize(math::cross(direction, [1.0, 0.0, 0.0])))?;
+
+		let along = (upper_length * upper_length + distance * distance - lower_length * lower_length) / (2.0 * distance);
+		let out = (upper_length * upper_length - along * along).max(0.0).sqrt();
+
+		Some(math::add(root, math::add(math::scale(direction, along), math::scale(bend, out))))
+	}
+}
+
+/// Every body's last good pose.
+#[derive(Default)]
+pub(crate) struct KinectInferrer {
+	bodies: Vec<(KinectBodyId, Box<BodyInference>)>,
+}
+impl KinectInferrer {
+	pub(crate) fn apply(&mut self, inference: &KinectInference, body: &mut KinectBody, learned: impl Fn(Joint) -> Option<f32>) {
+		let index = self.bodies.iter().position(|(id, _)| *id == body.id);
+
+		if matches!(body.frame.skeleton, KinectSkeleton::Untracked) {
+			if let Some(index) = index {
+				self.bodies.swap_remove(index);
+			}
+			return;
+		}
+
+		let state = match index {
+			Some(index) => &mut self.bodies[index].1,
+			None => {
+				self.bodies.push((body.id, Box::default()));
+				&mut self.bodies.last_mut().unwrap().1
+			}
+		};
+
+		state.apply(inference, &mut body.frame.skeleton, body.frame.timestamp, learned);
+	}
+
+	/// Forgets every body, e.g. when switching backends.
+	#[inline]
+	pub(crate) fn reset(&mut self) {
+		self.bodies.clear();
+	}
+}
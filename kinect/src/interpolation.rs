@@ -0,0 +1,233 @@
+use crate::{math, Joint, KinectBodyId, KinectFrame, KinectSkeleton, KinectTrackedExtendedSkeleton, KinectTrackedSkeleton, KinectTrackingState};
+use std::time::{Duration, Instant};
+
+/// How a [`KinectInterpolator`] turns the sensor's 30 FPS into a skeleton for any moment, e.g. every rendered frame or every tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectInterpolation {
+	/// How far behind the latest frame to sample. Samples within this much of the latest frame are interpolated from the last two
+	/// frames, which is smooth but adds latency. Later samples are extrapolated instead.
+	///
+	/// Only the last two frames are kept, so anything beyond one frame (about 33ms) just adds latency.
+	pub delay: Duration,
+	/// How far past the latest frame to extrapolate at most, after which joints hold still until the next frame arrives.
+	pub max_extrapolation: Duration,
+}
+impl Default for KinectInterpolation {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			delay: Duration::from_millis(16),
+			max_extrapolation: Duration::from_millis(50),
+		}
+	}
+}
+impl KinectInterpolation {
+	#[inline]
+	pub const fn with_delay(mut self, delay: Duration) -> Self {
+		self.delay = delay;
+		self
+	}
+
+	#[inline]
+	pub const fn with_max_extrapolation(mut self, max_extrapolation: Duration) -> Self {
+		self.max_extrapolation = max_extrapolation;
+		self
+	}
+}
+
+/// Frames further apart than this aren't interpolated between, e.g. after the body was lost for a while.
+const MAX_FRAME_GAP: f64 = 0.2;
+
+/// How quickly the sensor's clock is allowed to drift from ours, in seconds per second.
+const MAX_CLOCK_DRIFT: f64 = 0.01;
+
+#[derive(Clone, Copy, Debug)]
+struct Clock {
+	epoch: Instant,
+	/// When we received the latest frame, in seconds since `epoch`.
+	received: f64,
+	/// Our time minus the sensor's time, in seconds. Delivery can only make frames late, so this is the smallest difference seen.
+	offset: f64,
+}
+
+/// Keeps the selected body's last two frames, and samples a skeleton between or just past them at any moment.
+///
+/// Frames are placed on our own clock using their sensor timestamps rather than when they happened to be received, so uneven delivery
+/// doesn't show up as uneven motion.
+///
+/// [`KinectHandle`](crate::KinectHandle) keeps one of these up to date, see [`KinectHandle::sample`](crate::KinectHandle::sample).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KinectInterpolator {
+	interpolation: KinectInterpolation,
+	body: Option<KinectBodyId>,
+	previous: Option<KinectFrame>,
+	latest: Option<KinectFrame>,
+	clock: Option<Clock>,
+}
+impl KinectInterpolator {
+	#[inline]
+	pub fn new(interpolation: KinectInterpolation) -> Self {
+		Self {
+			interpolation,
+			..Default::default()
+		}
+	}
+
+	#[inline]
+	pub fn interpolation(&self) -> &KinectInterpolation {
+		&self.interpolation
+	}
+
+	#[inline]
+	pub fn set_interpolation(&mut self, interpolation: KinectInterpolation) {
+		self.interpolation = interpolation;
+	}
+
+	/// Adds `body`'s latest frame, which was received at `received`. Frames from a different body, or from before the latest frame
+	/// (e.g. from another backend), start over.
+	pub fn push(&mut self, body: Option<KinectBodyId>, frame: KinectFrame, received: Instant) {
+		let restart = self.body != body || self.latest.is_some_and(|latest| frame.timestamp <= latest.timestamp);
+
+		if restart {
+			self.reset();
+			self.body = body;
+		}
+
+		self.previous = self
+			.latest
+			.filter(|latest| (frame.timestamp - latest.timestamp).as_secs_f64() <= MAX_FRAME_GAP);
+		self.latest = Some(frame);
+
+		let timestamp = frame.timestamp.as_secs_f64();
+		self.clock = Some(match self.clock {
+			Some(clock) => {
+				let received = received.saturating_duration_since(clock.epoch).as_secs_f64();
+				let offset = received - timestamp;
+				let drift = (received - clock.received).max(0.0) * MAX_CLOCK_DRIFT;
+
+				Clock {
+					epoch: clock.epoch,
+					received,
+					offset: offset.min(clock.offset + drift),
+				}
+			}
+
+			None => Clock {
+				epoch: received,
+				received: 0.0,
+				offset: -timestamp,
+			},
+		});
+	}
+
+	/// Forgets every frame.
+	#[inline]
+	pub fn reset(&mut self) {
+		*self = Self::new(self.interpolation);
+	}
+
+	/// The latest frame, as it was pushed.
+	#[inline]
+	pub fn latest(&self) -> Option<&KinectFrame> {
+		self.latest.as_ref()
+	}
+
+	/// The skeleton at `at`, less [`KinectInterpolation::delay`]. [`KinectSkeleton::Untracked`] if there isn't one.
+	///
+	/// Joint positions and velocities are interpolated, everything else comes from the nearest frame.
+	pub fn sample(&self, at: Instant) -> KinectSkeleton {
+		let (Some(latest), Some(clock)) = (self.latest, self.clock) else {
+			return KinectSkeleton::Untracked;
+		};
+
+		// Where `at` falls on the sensor's clock
+		let target = match at.checked_duration_since(clock.epoch) {
+			Some(at) => at.as_secs_f64(),
+			None => -clock.epoch.duration_since(at).as_secs_f64(),
+		} - clock.offset
+			- self.interpolation.delay.as_secs_f64();
+
+		let latest_time = latest.timestamp.as_secs_f64();
+
+		let previous = self.previous.filter(|previous| !matches!(previous.skeleton, KinectSkeleton::Untracked));
+
+		if target >= latest_time || previous.is_none() {
+			let ahead = (target - latest_time).clamp(0.0, self.interpolation.max_extrapolation.as_secs_f64()) as f32;
+			return Self::extrapolate(latest.skeleton, previous.as_ref(), &latest, ahead);
+		}
+
+		let previous = previous.unwrap();
+		let previous_time = previous.timestamp.as_secs_f64();
+		if target <= previous_time {
+			return previous.skeleton;
+		}
+
+		let t = ((target - previous_time) / (latest_time - previous_time)) as f32;
+		Self::interpolate(&previous.skeleton, &latest.skeleton, t)
+	}
+
+	fn interpolate(previous: &KinectSkeleton, latest: &KinectSkeleton, t: f32) -> KinectSkeleton {
+		let (mut skeleton, other) = if t < 0.5 { (*previous, latest) } else { (*latest, previous) };
+		let t = if t < 0.5 { t } else { 1.0 - t };
+
+		for joint in Joint::ALL {
+			if skeleton.tracking_state(joint) == KinectTrackingState::NotTracked || other.tracking_state(joint) == KinectTrackingState::NotTracked {
+				continue;
+			}
+
+			if let (Some(bone), Some(other)) = (skeleton.bone_mut(joint), other.bone(joint)) {
+				*bone = math::lerp(*bone, *other, t);
+			}
+		}
+
+		let other_velocity = |joint: Joint| other.velocity(joint).copied();
+
+		if let Some(velocities) = skeleton.tracked_mut().and_then(|tracked| tracked.velocities_mut()) {
+			for (velocity, joint) in velocities.iter_mut().zip(KinectTrackedSkeleton::JOINTS) {
+				if let Some(other) = other_velocity(*joint) {
+					*velocity = math::lerp(*velocity, other, t);
+				}
+			}
+		}
+
+		if let Some(velocities) = skeleton.extended_mut().and_then(|extended| extended.velocities_mut()) {
+			for (velocity, joint) in velocities.iter_mut().zip(KinectTrackedExtendedSkeleton::JOINTS) {
+				if let Some(other) = other_velocity(*joint) {
+					*velocity = math::lerp(*velocity, other, t);
+				}
+			}
+		}
+
+		skeleton
+	}
+
+	/// Moves every joint along its velocity for `ahead` seconds. Without velocities from [`Kinect`](crate::Kinect), they're worked out
+	/// from the previous frame.
+	fn extrapolate(mut skeleton: KinectSkeleton, previous: Option<&KinectFrame>, latest: &KinectFrame, ahead: f32) -> KinectSkeleton {
+		if ahead <= 0.0 {
+			return skeleton;
+		}
+
+		let dt = previous
+			.map(|previous| (latest.timestamp - previous.timestamp).as_secs_f32())
+			.unwrap_or_default();
+
+		for joint in Joint::ALL {
+			if skeleton.tracking_state(joint) == KinectTrackingState::NotTracked {
+				continue;
+			}
+
+			let velocity = skeleton.velocity(joint).copied().or_else(|| {
+				let previous = previous.filter(|previous| previous.skeleton.tracking_state(joint) != KinectTrackingState::NotTracked)?;
+				let (from, to) = (previous.skeleton.bone(joint)?, latest.skeleton.bone(joint)?);
+				(dt > 0.0).then(|| math::scale(math::sub(*to, *from), 1.0 / dt))
+			});
+
+			if let (Some(bone), Some(velocity)) = (skeleton.bone_mut(joint), velocity) {
+				*bone = math::add(*bone, math::scale(velocity, ahead));
+			}
+		}
+
+		skeleton
+	}
+}
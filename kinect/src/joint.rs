@@ -0,0 +1,217 @@
+use crate::{EXTENDED_SKELETON_BONE_COUNT, SKELETON_BONE_COUNT};
+
+macro_rules! joints {
+	($($joint:ident = $sensorbone:literal => $name:literal),*) => {
+		/// A skeleton joint, numbered the same way as Garry's Mod's `SENSORBONE` enum.
+		///
+		/// The first [`SKELETON_BONE_COUNT`] joints are available from every backend, the rest are "extended" joints which only some backends provide.
+		#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+		#[repr(u8)]
+		pub enum Joint {
+			$($joint = $sensorbone),*
+		}
+		impl Joint {
+			pub const COUNT: usize = [$(Joint::$joint),*].len();
+
+			/// Every joint, in `SENSORBONE` order.
+			pub const ALL: [Joint; Self::COUNT] = [$(Joint::$joint),*];
+
+			/// The joint's `SENSORBONE` name, e.g. `HAND_LEFT`.
+			pub const fn name(self) -> &'static str {
+				match self {
+					$(Joint::$joint => $name),*
+				}
+			}
+
+			/// Looks up a joint by its `SENSORBONE` name, ignoring case.
+			pub fn from_name(name: &str) -> Option<Self> {
+				Self::ALL.into_iter().find(|joint| joint.name().eq_ignore_ascii_case(name))
+			}
+		}
+	};
+}
+joints! {
+	HipCenter = 0 => "HIP",
+	Spine = 1 => "SPINE",
+	ShoulderCenter = 2 => "SHOULDER",
+	Head = 3 => "HEAD",
+	ShoulderLeft = 4 => "SHOULDER_LEFT",
+	ElbowLeft = 5 => "ELBOW_LEFT",
+	WristLeft = 6 => "WRIST_LEFT",
+	HandLeft = 7 => "HAND_LEFT",
+	ShoulderRight = 8 => "SHOULDER_RIGHT",
+	ElbowRight = 9 => "ELBOW_RIGHT",
+	WristRight = 10 => "WRIST_RIGHT",
+	HandRight = 11 => "HAND_RIGHT",
+	HipLeft = 12 => "HIP_LEFT",
+	KneeLeft = 13 => "KNEE_LEFT",
+	AnkleLeft = 14 => "ANKLE_LEFT",
+	FootLeft = 15 => "FOOT_LEFT",
+	HipRight = 16 => "HIP_RIGHT",
+	KneeRight = 17 => "KNEE_RIGHT",
+	AnkleRight = 18 => "ANKLE_RIGHT",
+	FootRight = 19 => "FOOT_RIGHT",
+
+	SpineBase = 20 => "SPINE_BASE",
+	Neck = 21 => "NECK",
+	SpineShoulder = 22 => "SPINE_SHOULDER",
+	HandTipLeft = 23 => "HAND_TIP_LEFT",
+	ThumbLeft = 24 => "THUMB_LEFT",
+	HandTipRight = 25 => "HAND_TIP_RIGHT",
+	ThumbRight = 26 => "THUMB_RIGHT"
+}
+impl Joint {
+	/// The joints stored in a [`KinectTrackedSkeleton`](crate::KinectTrackedSkeleton), in order.
+	pub const SKELETON: [Joint; SKELETON_BONE_COUNT] = {
+		let mut joints = [Joint::HipCenter; SKELETON_BONE_COUNT];
+		let mut i = 0;
+		while i < SKELETON_BONE_COUNT {
+			joints[i] = Self::ALL[i];
+			i += 1;
+		}
+		joints
+	};
+
+	/// The joints stored in a [`KinectTrackedExtendedSkeleton`](crate::KinectTrackedExtendedSkeleton), in order.
+	pub const EXTENDED: [Joint; EXTENDED_SKELETON_BONE_COUNT] = {
+		let mut joints = [Joint::SpineBase; EXTENDED_SKELETON_BONE_COUNT];
+		let mut i = 0;
+		while i < EXTENDED_SKELETON_BONE_COUNT {
+			joints[i] = Self::ALL[SKELETON_BONE_COUNT + i];
+			i += 1;
+		}
+		joints
+	};
+
+	#[inline]
+	pub fn iter() -> impl DoubleEndedIterator<Item = Joint> + ExactSizeIterator {
+		Self::ALL.into_iter()
+	}
+
+	#[inline]
+	pub const fn from_sensor_bone(sensor_bone: usize) -> Option<Self> {
+		if sensor_bone < Self::COUNT {
+			Some(Self::ALL[sensor_bone])
+		} else {
+			None
+		}
+	}
+
+	#[inline]
+	pub const fn sensor_bone(self) -> usize {
+		self as usize
+	}
+
+	#[inline]
+	pub const fn is_extended(self) -> bool {
+		self.sensor_bone() >= SKELETON_BONE_COUNT
+	}
+
+	/// The joint this one hangs off, going outwards from [`Joint::HipCenter`]. Parents always come before their children in [`Joint::ALL`].
+	pub const fn parent(self) -> Option<Self> {
+		Some(match self {
+			Joint::HipCenter => return None,
+			Joint::Spine => Joint::HipCenter,
+			Joint::ShoulderCenter => Joint::Spine,
+			Joint::Head => Joint::ShoulderCenter,
+			Joint::ShoulderLeft => Joint::ShoulderCenter,
+			Joint::ElbowLeft => Joint::ShoulderLeft,
+			Joint::WristLeft => Joint::ElbowLeft,
+			Joint::HandLeft => Joint::WristLeft,
+			Joint::ShoulderRight => Joint::ShoulderCenter,
+			Joint::ElbowRight => Joint::ShoulderRight,
+			Joint::WristRight => Joint::ElbowRight,
+			Joint::HandRight => Joint::WristRight,
+			Joint::HipLeft => Joint::HipCenter,
+			Joint::KneeLeft => Joint::HipLeft,
+			Joint::AnkleLeft => Joint::KneeLeft,
+			Joint::FootLeft => Joint::AnkleLeft,
+			Joint::HipRight => Joint::HipCenter,
+			Joint::KneeRight => Joint::HipRight,
+			Joint::AnkleRight => Joint::KneeRight,
+			Joint::FootRight => Joint::AnkleRight,
+			Joint::SpineBase => Joint::HipCenter,
+			Joint::Neck => Joint::ShoulderCenter,
+			Joint::SpineShoulder => Joint::ShoulderCenter,
+			Joint::HandTipLeft => Joint::HandLeft,
+			Joint::ThumbLeft => Joint::HandLeft,
+			Joint::HandTipRight => Joint::HandRight,
+			Joint::ThumbRight => Joint::HandRight,
+		})
+	}
+
+	/// The same joint on the other side of the body, or itself if it's in the middle.
+	pub const fn mirrored(self) -> Self {
+		match self {
+			Joint::ShoulderLeft => Joint::ShoulderRight,
+			Joint::ElbowLeft => Joint::ElbowRight,
+			Joint::WristLeft => Joint::WristRight,
+			Joint::HandLeft => Joint::HandRight,
+			Joint::ShoulderRight => Joint::ShoulderLeft,
+			Joint::ElbowRight => Joint::ElbowLeft,
+			Joint::WristRight => Joint::WristLeft,
+			Joint::HandRight => Joint::HandLeft,
+			Joint::HipLeft => Joint::HipRight,
+			Joint::KneeLeft => Joint::KneeRight,
+			Joint::AnkleLeft => Joint::AnkleRight,
+			Joint::FootLeft => Joint::FootRight,
+			Joint::HipRight => Joint::HipLeft,
+			Joint::KneeRight => Joint::KneeLeft,
+			Joint::AnkleRight => Joint::AnkleLeft,
+			Joint::FootRight => Joint::FootLeft,
+			Joint::HandTipLeft => Joint::HandTipRight,
+			Joint::ThumbLeft => Joint::ThumbRight,
+			Joint::HandTipRight => Joint::HandTipLeft,
+			Joint::ThumbRight => Joint::ThumbLeft,
+			joint => joint,
+		}
+	}
+
+	/// Where this joint lives in a [`KinectTrackedSkeleton`](crate::KinectTrackedSkeleton), if it isn't an extended joint.
+	#[inline]
+	pub const fn skeleton_index(self) -> Option<usize> {
+		if !self.is_extended() {
+			Some(self.sensor_bone())
+		} else {
+			None
+		}
+	}
+
+	/// Where this joint lives in a [`KinectTrackedExtendedSkeleton`](crate::KinectTrackedExtendedSkeleton), if it is an extended joint.
+	#[inline]
+	pub const fn extended_index(self) -> Option<usize> {
+		if self.is_extended() {
+			Some(self.sensor_bone() - SKELETON_BONE_COUNT)
+		} else {
+			None
+		}
+	}
+}
+impl TryFrom<usize> for Joint {
+	type Error = ();
+
+	#[inline]
+	fn try_from(sensor_bone: usize) -> Result<Self, Self::Error> {
+		Self::from_sensor_bone(sensor_bone).ok_or(())
+	}
+}
+impl From<Joint> for usize {
+	#[inline]
+	fn from(joint: Joint) -> Self {
+		joint.sensor_bone()
+	}
+}
+impl std::str::FromStr for Joint {
+	type Err = ();
+
+	#[inline]
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		Self::from_name(name).ok_or(())
+	}
+}
+impl std::fmt::Display for Joint {
+	#[inline]
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self.name())
+	}
+}
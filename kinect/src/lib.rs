@@ -1,12 +1,125 @@
+use std::{
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+pub mod abi;
+mod calibration;
+mod discovery;
+mod filter;
+mod floor;
+mod gesture;
+mod handle;
+mod inference;
+mod interpolation;
+mod joint;
+mod mailbox;
+mod math;
+mod metrics;
+mod motion;
+mod policy;
+mod retarget;
+mod rules;
+mod subscription;
+mod transform;
+mod validation;
+pub use calibration::{KinectBodyProfile, KinectCalibrationEvent, KinectCalibrationPose, KinectCalibrationState, BODY_PROFILE_PATH};
+pub use discovery::*;
+pub use filter::{KinectFilter, KinectFilterConfig, KinectHoltParameters, KinectOneEuroParameters};
+pub use floor::{KinectFloorPlane, KinectReferenceFrame, KinectSensorOrientation};
+pub use gesture::{KinectGestureEvent, KinectGestureRecognition, KinectGestureRecorder, KinectGestureTemplate, GESTURE_TEMPLATE_DIRECTORY};
+pub use handle::{KinectHandle, KinectSnapshot};
+pub use inference::KinectInference;
+pub use interpolation::{KinectInterpolation, KinectInterpolator};
+pub use joint::Joint;
+pub use mailbox::Mailbox;
+pub use metrics::KinectBodyMetrics;
+pub use motion::KinectMotion;
+pub use policy::*;
+pub use retarget::{KinectRetargetRule, KinectRetargetSource, KinectRetargetTable};
+pub use rules::{KinectPoseEvent, KinectPoseRule, KinectPredicate, KinectRelation, KinectRules, KinectSequenceRule, RULES_PATH};
+pub use subscription::{KinectBackpressure, KinectSubscription, KinectSubscriptionId};
+pub use transform::{KinectAxis, KinectTransform, HAMMER_UNITS_PER_METRE};
+pub use validation::KinectValidation;
+
 pub const SKELETON_BONE_COUNT: usize = 20;
 pub const EXTENDED_SKELETON_BONE_COUNT: usize = 7;
 
 pub type KinectSkeletonRawBones = [[f32; 3]; SKELETON_BONE_COUNT];
 pub type KinectExtendedSkeletonRawBones = [[f32; 3]; EXTENDED_SKELETON_BONE_COUNT];
 
+pub type KinectSkeletonTrackingStates = [KinectTrackingState; SKELETON_BONE_COUNT];
+pub type KinectExtendedSkeletonTrackingStates = [KinectTrackingState; EXTENDED_SKELETON_BONE_COUNT];
+
+/// Absolute joint orientations as `[x, y, z, w]` quaternions, in the same coordinate space as the bone positions.
+pub type KinectSkeletonRawOrientations = [[f32; 4]; SKELETON_BONE_COUNT];
+pub type KinectExtendedSkeletonRawOrientations = [[f32; 4]; EXTENDED_SKELETON_BONE_COUNT];
+
 pub trait KinectBackend {
-	fn poll(&mut self) -> Option<KinectSkeleton>;
-	fn available(&self) -> bool;
+	/// Returns the next body update, if any. A [`KinectSkeleton::Untracked`] skeleton means the body was lost.
+	fn poll(&mut self) -> Option<KinectBody>;
+	fn state(&self) -> KinectBackendState;
+
+	/// The sensor's latest estimate of the floor, if it has found it.
+	#[inline]
+	fn floor_plane(&self) -> Option<KinectFloorPlane> {
+		None
+	}
+
+	/// Which way is up for the sensor. Defaults to the floor's normal.
+	#[inline]
+	fn sensor_orientation(&self) -> Option<KinectSensorOrientation> {
+		self.floor_plane().map(KinectSensorOrientation::from)
+	}
+}
+
+/// Where a backend is in its lifecycle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum KinectBackendState {
+	/// No sensor is connected. The backend will pick one up by itself when it is plugged in.
+	#[default]
+	NotConnected = 0,
+	/// A sensor was found and is starting up.
+	Initializing = 1,
+	/// The sensor is delivering frames.
+	Ready = 2,
+	/// The backend stopped working and can't recover by itself. [`Kinect`] will unload it and try loading it again later.
+	Lost = 3,
+}
+impl KinectBackendState {
+	/// Converts from the `#[repr(u32)]` value. Anything unknown is treated as [`KinectBackendState::Lost`].
+	#[inline]
+	pub const fn from_raw(state: u32) -> Self {
+		match state {
+			0 => Self::NotConnected,
+			1 => Self::Initializing,
+			2 => Self::Ready,
+			_ => Self::Lost,
+		}
+	}
+}
+
+/// Identifies a body for as long as the sensor keeps tracking it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KinectBodyId(pub u64);
+
+#[derive(Clone, Copy, Debug)]
+pub struct KinectBody {
+	pub id: KinectBodyId,
+	pub frame: KinectFrame,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KinectFrame {
+	pub skeleton: KinectSkeleton,
+	/// Monotonic capture time, taken from the sensor's own clock where the SDK provides one.
+	///
+	/// Only comparable between frames from the same backend.
+	pub timestamp: Duration,
+	/// Incremented by the backend once per sensor frame, so bodies captured in the same frame share a sequence number.
+	pub sequence: u64,
 }
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -18,198 +131,1301 @@ pub enum KinectSkeleton {
 	TrackedExtended(KinectTrackedSkeleton, KinectTrackedExtendedSkeleton),
 }
 
-#[derive(Clone, Copy)]
-pub union KinectTrackedSkeleton {
-	raw_bones: KinectSkeletonRawBones,
-	bones: KinectSkeletonBones,
-}
-impl KinectTrackedSkeleton {
-	#[inline(always)]
-	pub fn from_raw_bones(raw_bones: KinectSkeletonRawBones) -> Self {
-		Self { raw_bones }
+impl KinectSkeleton {
+	#[inline]
+	pub fn tracked(&self) -> Option<&KinectTrackedSkeleton> {
+		match self {
+			KinectSkeleton::Tracked(skeleton) | KinectSkeleton::TrackedExtended(skeleton, _) => Some(skeleton),
+			KinectSkeleton::Untracked => None,
+		}
 	}
 
-	#[inline(always)]
-	pub fn from_named_bones(bones: KinectSkeletonBones) -> Self {
-		Self { bones }
+	#[inline]
+	pub fn extended(&self) -> Option<&KinectTrackedExtendedSkeleton> {
+		match self {
+			KinectSkeleton::TrackedExtended(_, extended) => Some(extended),
+			_ => None,
+		}
 	}
 
-	#[inline(always)]
-	pub fn raw_bones(&self) -> &KinectSkeletonRawBones {
-		unsafe { &self.raw_bones }
+	#[inline]
+	pub fn tracked_mut(&mut self) -> Option<&mut KinectTrackedSkeleton> {
+		match self {
+			KinectSkeleton::Tracked(skeleton) | KinectSkeleton::TrackedExtended(skeleton, _) => Some(skeleton),
+			KinectSkeleton::Untracked => None,
+		}
+	}
+
+	#[inline]
+	pub fn extended_mut(&mut self) -> Option<&mut KinectTrackedExtendedSkeleton> {
+		match self {
+			KinectSkeleton::TrackedExtended(_, extended) => Some(extended),
+			_ => None,
+		}
 	}
 
-	#[inline(always)]
-	pub fn bones(&self) -> &KinectSkeletonBones {
-		unsafe { &self.bones }
+	/// Returns `None` if the skeleton isn't tracked or doesn't have this joint.
+	#[inline]
+	pub fn bone(&self, joint: Joint) -> Option<&[f32; 3]> {
+		if joint.is_extended() {
+			self.extended()?.get(joint)
+		} else {
+			self.tracked()?.get(joint)
+		}
+	}
+
+	#[inline]
+	pub fn bone_mut(&mut self, joint: Joint) -> Option<&mut [f32; 3]> {
+		if joint.is_extended() {
+			self.extended_mut()?.get_mut(joint)
+		} else {
+			self.tracked_mut()?.get_mut(joint)
+		}
+	}
+
+	#[inline]
+	pub fn tracking_state(&self, joint: Joint) -> KinectTrackingState {
+		if joint.is_extended() {
+			self.extended().and_then(|extended| extended.tracking_state(joint))
+		} else {
+			self.tracked().and_then(|skeleton| skeleton.tracking_state(joint))
+		}
+		.unwrap_or_default()
+	}
+
+	#[inline]
+	pub fn tracking_state_mut(&mut self, joint: Joint) -> Option<&mut KinectTrackingState> {
+		if joint.is_extended() {
+			self.extended_mut()?.tracking_state_mut(joint)
+		} else {
+			self.tracked_mut()?.tracking_state_mut(joint)
+		}
+	}
+
+	/// Returns `None` if the skeleton isn't tracked or the backend can't track hands.
+	#[inline]
+	pub fn hands(&self) -> Option<&KinectHands> {
+		self.tracked()?.hands()
+	}
+
+	#[inline]
+	pub fn orientation(&self, joint: Joint) -> Option<&[f32; 4]> {
+		if joint.is_extended() {
+			self.extended()?.orientation(joint)
+		} else {
+			self.tracked()?.orientation(joint)
+		}
+	}
+
+	/// Returns `None` if the skeleton isn't tracked, doesn't have this joint or has no motion yet, see [`KinectMotion`].
+	#[inline]
+	pub fn velocity(&self, joint: Joint) -> Option<&[f32; 3]> {
+		if joint.is_extended() {
+			self.extended()?.velocity(joint)
+		} else {
+			self.tracked()?.velocity(joint)
+		}
+	}
+
+	#[inline]
+	pub fn acceleration(&self, joint: Joint) -> Option<&[f32; 3]> {
+		if joint.is_extended() {
+			self.extended()?.acceleration(joint)
+		} else {
+			self.tracked()?.acceleration(joint)
+		}
 	}
 }
-impl std::fmt::Debug for KinectTrackedSkeleton {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		self.bones().fmt(f)
+
+/// How sure the sensor is about a joint. Sorts by [`rank`](Self::rank), from least to most trustworthy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum KinectTrackingState {
+	#[default]
+	NotTracked = 0,
+	Inferred = 1,
+	Tracked = 2,
+	/// Filled in by [`Kinect`] from the rest of the body because the sensor lost the joint, see [`KinectInference`]. Backends never report
+	/// this.
+	Synthesized = 3,
+}
+impl PartialOrd for KinectTrackingState {
+	#[inline]
+	fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+		Some(self.cmp(other))
 	}
 }
-impl Default for KinectTrackedSkeleton {
-	fn default() -> Self {
-		Self {
-			raw_bones: [[0.0; 3]; SKELETON_BONE_COUNT],
+impl Ord for KinectTrackingState {
+	#[inline]
+	fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+		self.rank().cmp(&other.rank())
+	}
+}
+impl KinectTrackingState {
+	/// How far the joint can be trusted, from 0 for [`NotTracked`](Self::NotTracked) to 3 for [`Tracked`](Self::Tracked).
+	///
+	/// Unlike the `#[repr(u8)]` value, this puts [`Synthesized`](Self::Synthesized) joints, which are worked out from a pose that may be a
+	/// couple of seconds old, below the sensor's own live guesses.
+	#[inline]
+	pub const fn rank(self) -> u8 {
+		match self {
+			Self::NotTracked => 0,
+			Self::Synthesized => 1,
+			Self::Inferred => 2,
+			Self::Tracked => 3,
+		}
+	}
+
+	/// Converts from the `#[repr(u8)]` value. Anything unknown is treated as [`KinectTrackingState::NotTracked`].
+	#[inline]
+	pub const fn from_raw(state: u8) -> Self {
+		match state {
+			2 => Self::Tracked,
+			1 => Self::Inferred,
+			_ => Self::NotTracked,
 		}
 	}
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct KinectSkeletonBones {
-	pub hip_center: [f32; 3],
-	pub spine: [f32; 3],
-	pub shoulder_center: [f32; 3],
-	pub head: [f32; 3],
-	pub shoulder_left: [f32; 3],
-	pub elbow_left: [f32; 3],
-	pub wrist_left: [f32; 3],
-	pub hand_left: [f32; 3],
-	pub shoulder_right: [f32; 3],
-	pub elbow_right: [f32; 3],
-	pub wrist_right: [f32; 3],
-	pub hand_right: [f32; 3],
-	pub hip_left: [f32; 3],
-	pub knee_left: [f32; 3],
-	pub ankle_left: [f32; 3],
-	pub foot_left: [f32; 3],
-	pub hip_right: [f32; 3],
-	pub knee_right: [f32; 3],
-	pub ankle_right: [f32; 3],
-	pub foot_right: [f32; 3],
-}
-
-#[derive(Clone, Copy)]
-pub union KinectTrackedExtendedSkeleton {
-	raw_bones: KinectExtendedSkeletonRawBones,
-	bones: KinectExtendedSkeletonBones,
-}
-impl KinectTrackedExtendedSkeleton {
-	#[inline(always)]
-	pub fn from_raw_bones(raw_bones: KinectExtendedSkeletonRawBones) -> Self {
-		Self { raw_bones }
-	}
-
-	#[inline(always)]
-	pub fn from_named_bones(bones: KinectExtendedSkeletonBones) -> Self {
-		Self { bones }
-	}
-
-	#[inline(always)]
-	pub fn raw_bones(&self) -> &KinectExtendedSkeletonRawBones {
-		unsafe { &self.raw_bones }
-	}
-
-	#[inline(always)]
-	pub fn bones(&self) -> &KinectExtendedSkeletonBones {
-		unsafe { &self.bones }
-	}
-}
-impl std::fmt::Debug for KinectTrackedExtendedSkeleton {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		self.bones().fmt(f)
+/// What a hand is doing. Only some sensors can tell.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum KinectHandState {
+	#[default]
+	Unknown = 0,
+	NotTracked = 1,
+	Open = 2,
+	Closed = 3,
+	/// Index and middle finger extended, like a pointing gesture.
+	Lasso = 4,
+}
+impl KinectHandState {
+	/// Converts from the `#[repr(u8)]` value. Anything unknown is treated as [`KinectHandState::Unknown`].
+	#[inline]
+	pub const fn from_raw(state: u8) -> Self {
+		match state {
+			1 => Self::NotTracked,
+			2 => Self::Open,
+			3 => Self::Closed,
+			4 => Self::Lasso,
+			_ => Self::Unknown,
+		}
 	}
 }
-impl Default for KinectTrackedExtendedSkeleton {
-	fn default() -> Self {
-		Self {
-			raw_bones: [[0.0; 3]; EXTENDED_SKELETON_BONE_COUNT],
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum KinectHandConfidence {
+	#[default]
+	Low = 0,
+	High = 1,
+}
+impl KinectHandConfidence {
+	/// Converts from the `#[repr(u8)]` value. Anything unknown is treated as [`KinectHandConfidence::Low`].
+	#[inline]
+	pub const fn from_raw(confidence: u8) -> Self {
+		match confidence {
+			1 => Self::High,
+			_ => Self::Low,
 		}
 	}
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct KinectExtendedSkeletonBones {
-	pub spine_base: [f32; 3],
-	pub neck: [f32; 3],
-	pub spine_shoulder: [f32; 3],
-	pub hand_tip_left: [f32; 3],
-	pub thumb_left: [f32; 3],
-	pub hand_tip_right: [f32; 3],
-	pub thumb_right: [f32; 3],
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct KinectHand {
+	pub state: KinectHandState,
+	pub confidence: KinectHandConfidence,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct KinectHands {
+	pub left: KinectHand,
+	pub right: KinectHand,
+}
+impl KinectHands {
+	/// Returns `None` unless `joint` is [`Joint::HandLeft`] or [`Joint::HandRight`].
+	#[inline]
+	pub fn get(&self, joint: Joint) -> Option<&KinectHand> {
+		match joint {
+			Joint::HandLeft => Some(&self.left),
+			Joint::HandRight => Some(&self.right),
+			_ => None,
+		}
+	}
+}
+
+macro_rules! tracked_skeleton {
+	($name:ident { joints: $joints:expr, index: $index:ident, bones: $bones:ty, tracking_states: $tracking_states:ty, orientations: $orientations:ty $(, hands: $hands:ty)? $(,)? }) => {
+		#[derive(Clone, Copy, Default)]
+		pub struct $name {
+			bones: $bones,
+			tracking_states: $tracking_states,
+			orientations: Option<$orientations>,
+			velocities: Option<$bones>,
+			accelerations: Option<$bones>,
+			$(hands: Option<$hands>,)?
+		}
+		impl $name {
+			/// The joints stored in this skeleton, in order.
+			pub const JOINTS: &'static [Joint] = &$joints;
+
+			#[inline(always)]
+			pub fn from_raw_bones(raw_bones: $bones, tracking_states: $tracking_states) -> Self {
+				Self {
+					bones: raw_bones,
+					tracking_states,
+					orientations: None,
+					velocities: None,
+					accelerations: None,
+					$(hands: Option::<$hands>::None,)?
+				}
+			}
+
+			#[inline(always)]
+			pub fn with_orientations(mut self, orientations: $orientations) -> Self {
+				self.orientations = Some(orientations);
+				self
+			}
+
+			#[inline(always)]
+			pub fn raw_bones(&self) -> &$bones {
+				&self.bones
+			}
+
+			#[inline(always)]
+			pub fn raw_bones_mut(&mut self) -> &mut $bones {
+				&mut self.bones
+			}
+
+			#[inline(always)]
+			pub fn tracking_states(&self) -> &$tracking_states {
+				&self.tracking_states
+			}
+
+			#[inline(always)]
+			pub fn tracking_states_mut(&mut self) -> &mut $tracking_states {
+				&mut self.tracking_states
+			}
+
+			/// Not every backend can provide joint orientations.
+			#[inline(always)]
+			pub fn orientations(&self) -> Option<&$orientations> {
+				self.orientations.as_ref()
+			}
+
+			#[inline(always)]
+			pub fn orientations_mut(&mut self) -> Option<&mut $orientations> {
+				self.orientations.as_mut()
+			}
+
+			#[inline(always)]
+			pub fn with_motion(mut self, velocities: $bones, accelerations: $bones) -> Self {
+				self.velocities = Some(velocities);
+				self.accelerations = Some(accelerations);
+				self
+			}
+
+			/// How fast each joint is moving, in the bones' units per second. Set by [`Kinect`], see [`KinectMotion`].
+			#[inline(always)]
+			pub fn velocities(&self) -> Option<&$bones> {
+				self.velocities.as_ref()
+			}
+
+			#[inline(always)]
+			pub fn velocities_mut(&mut self) -> Option<&mut $bones> {
+				self.velocities.as_mut()
+			}
+
+			/// In the bones' units per second squared, see [`velocities`](Self::velocities).
+			#[inline(always)]
+			pub fn accelerations(&self) -> Option<&$bones> {
+				self.accelerations.as_ref()
+			}
+
+			#[inline(always)]
+			pub fn accelerations_mut(&mut self) -> Option<&mut $bones> {
+				self.accelerations.as_mut()
+			}
+
+			$(
+				#[inline(always)]
+				pub fn with_hands(mut self, hands: $hands) -> Self {
+					self.hands = Some(hands);
+					self
+				}
+
+				/// Not every backend can track hands.
+				#[inline(always)]
+				pub fn hands(&self) -> Option<&$hands> {
+					self.hands.as_ref()
+				}
+
+				#[inline(always)]
+				pub fn hands_mut(&mut self) -> Option<&mut $hands> {
+					self.hands.as_mut()
+				}
+			)?
+
+			/// Returns `None` if the joint isn't part of this skeleton.
+			#[inline]
+			pub fn get(&self, joint: Joint) -> Option<&[f32; 3]> {
+				self.bones.get(joint.$index()?)
+			}
+
+			#[inline]
+			pub fn get_mut(&mut self, joint: Joint) -> Option<&mut [f32; 3]> {
+				self.bones.get_mut(joint.$index()?)
+			}
+
+			#[inline]
+			pub fn tracking_state(&self, joint: Joint) -> Option<KinectTrackingState> {
+				self.tracking_states.get(joint.$index()?).copied()
+			}
+
+			#[inline]
+			pub fn tracking_state_mut(&mut self, joint: Joint) -> Option<&mut KinectTrackingState> {
+				self.tracking_states.get_mut(joint.$index()?)
+			}
+
+			#[inline]
+			pub fn orientation(&self, joint: Joint) -> Option<&[f32; 4]> {
+				self.orientations.as_ref()?.get(joint.$index()?)
+			}
+
+			#[inline]
+			pub fn velocity(&self, joint: Joint) -> Option<&[f32; 3]> {
+				self.velocities.as_ref()?.get(joint.$index()?)
+			}
+
+			#[inline]
+			pub fn acceleration(&self, joint: Joint) -> Option<&[f32; 3]> {
+				self.accelerations.as_ref()?.get(joint.$index()?)
+			}
+
+			/// Swaps every joint's data with the joint on the other side of the body, see [`Joint::mirrored`].
+			pub(crate) fn swap_sides(&mut self) {
+				for (i, joint) in Self::JOINTS.iter().enumerate() {
+					let Some(j) = joint.mirrored().$index().filter(|j| *j > i) else {
+						continue;
+					};
+
+					self.bones.swap(i, j);
+					self.tracking_states.swap(i, j);
+					if let Some(orientations) = &mut self.orientations {
+						orientations.swap(i, j);
+					}
+					if let Some(velocities) = &mut self.velocities {
+						velocities.swap(i, j);
+					}
+					if let Some(accelerations) = &mut self.accelerations {
+						accelerations.swap(i, j);
+					}
+				}
+			}
+
+			#[inline]
+			pub fn iter(&self) -> impl Iterator<Item = (Joint, &[f32; 3])> + '_ {
+				Self::JOINTS.iter().copied().zip(self.bones.iter())
+			}
+		}
+		impl std::ops::Index<Joint> for $name {
+			type Output = [f32; 3];
+
+			#[inline]
+			fn index(&self, joint: Joint) -> &Self::Output {
+				match self.get(joint) {
+					Some(bone) => bone,
+					None => panic!("{joint} is not part of {}", stringify!($name)),
+				}
+			}
+		}
+		impl std::ops::IndexMut<Joint> for $name {
+			#[inline]
+			fn index_mut(&mut self, joint: Joint) -> &mut Self::Output {
+				match self.get_mut(joint) {
+					Some(bone) => bone,
+					None => panic!("{joint} is not part of {}", stringify!($name)),
+				}
+			}
+		}
+		impl std::fmt::Debug for $name {
+			fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+				let mut f = f.debug_struct(stringify!($name));
+				f.field("bones", &DebugJoints(Self::JOINTS, &self.bones))
+					.field("tracking_states", &DebugJoints(Self::JOINTS, &self.tracking_states))
+					.field(
+						"orientations",
+						&self
+							.orientations
+							.as_ref()
+							.map(|orientations| DebugJoints(Self::JOINTS, orientations)),
+					)
+					.field("velocities", &self.velocities.as_ref().map(|velocities| DebugJoints(Self::JOINTS, velocities)))
+					.field(
+						"accelerations",
+						&self.accelerations.as_ref().map(|accelerations| DebugJoints(Self::JOINTS, accelerations)),
+					);
+				$(f.field("hands", &self.hands as &Option<$hands>);)?
+				f.finish()
+			}
+		}
+	};
+}
+tracked_skeleton!(KinectTrackedSkeleton {
+	joints: Joint::SKELETON,
+	index: skeleton_index,
+	bones: KinectSkeletonRawBones,
+	tracking_states: KinectSkeletonTrackingStates,
+	orientations: KinectSkeletonRawOrientations,
+	hands: KinectHands,
+});
+tracked_skeleton!(KinectTrackedExtendedSkeleton {
+	joints: Joint::EXTENDED,
+	index: extended_index,
+	bones: KinectExtendedSkeletonRawBones,
+	tracking_states: KinectExtendedSkeletonTrackingStates,
+	orientations: KinectExtendedSkeletonRawOrientations,
+});
+
+struct DebugJoints<'a, T>(&'a [Joint], &'a [T]);
+impl<T: std::fmt::Debug> std::fmt::Debug for DebugJoints<'_, T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_map().entries(self.0.iter().copied().map(Joint::name).zip(self.1)).finish()
+	}
 }
 
 pub struct DynKinectBackend {
-	backend: Box<dyn KinectBackend>,
+	name: String,
+	capabilities: abi::KinectBackendCapabilities,
+	instance: abi::KinectBackendInstance,
+	/// Must outlive the backend
+	_waker: Arc<abi::HostWaker>,
 	_lib: libloading::Library,
 }
+/// Why [`DynKinectBackend::load`] failed.
+enum KinectBackendLoadError {
+	/// The library isn't a backend we can talk to, there's no point trying again.
+	Incompatible,
+	/// The library or the backend failed to initialise, it might work next time.
+	Failed,
+}
+
 impl DynKinectBackend {
-	unsafe fn load(backend: &str) -> Option<Self> {
-		log::info!("{}: Loading...", backend);
+	/// Failures are logged as warnings, unless `retrying`, in which case they were already logged the first time round.
+	unsafe fn load(path: &Path, retrying: bool, waker: &Arc<abi::HostWaker>) -> Result<Self, KinectBackendLoadError> {
+		let backend = path.display();
+		let level = if retrying { log::Level::Debug } else { log::Level::Warn };
 
-		type GmKinectDynInit = unsafe extern "Rust" fn(&'static dyn log::Log) -> Result<Box<dyn KinectBackend>, std::io::Error>;
+		log::log!(if retrying { log::Level::Debug } else { log::Level::Info }, "{}: Loading...", backend);
 
-		let lib = libloading::Library::new(backend);
-		let lib = lib.and_then(|lib| Ok((*lib.get::<GmKinectDynInit>(b"gmcl_rekinect_init")?, lib)));
+		let lib = match libloading::Library::new(path) {
+			Ok(lib) => lib,
+			Err(err) => {
+				log::log!(level, "{}: {err:?}", backend);
+				return Err(KinectBackendLoadError::Failed);
+			}
+		};
 
-		match lib {
-			Ok((init, lib)) => match init(log::logger()) {
-				Ok(kinect) => {
-					log::info!("{}: OK!", backend);
-					Some(Self { _lib: lib, backend: kinect })
-				}
+		let Ok(info) = lib.get::<abi::KinectBackendInfoFn>(b"gmcl_rekinect_backend_info") else {
+			log::warn!("{}: Not a gmcl_rekinect backend, or built for an older version of gmcl_rekinect", backend);
+			return Err(KinectBackendLoadError::Incompatible);
+		};
 
-				Err(err) => {
-					log::warn!("{}: {err:?}", backend);
-					None
-				}
-			},
+		let info = match info().as_ref() {
+			Some(info) if info.abi_version == abi::KINECT_BACKEND_ABI_VERSION => info,
+
+			Some(info) => {
+				log::warn!(
+					"{}: Backend was built for ABI version {}, but gmcl_rekinect expects ABI version {}",
+					backend,
+					info.abi_version,
+					abi::KINECT_BACKEND_ABI_VERSION
+				);
+				return Err(KinectBackendLoadError::Incompatible);
+			}
 
+			None => {
+				log::warn!("{}: gmcl_rekinect_backend_info() returned NULL", backend);
+				return Err(KinectBackendLoadError::Incompatible);
+			}
+		};
+
+		let (name, capabilities) = (info.name().to_owned(), info.capabilities);
+
+		let create = match lib.get::<abi::KinectBackendCreateFn>(b"gmcl_rekinect_backend_create") {
+			Ok(create) => create,
 			Err(err) => {
 				log::warn!("{}: {err:?}", backend);
-				None
+				return Err(KinectBackendLoadError::Incompatible);
 			}
+		};
+
+		let host = abi::KinectBackendHost {
+			abi_version: abi::KINECT_BACKEND_ABI_VERSION,
+			log: abi::host_log,
+			wake: abi::host_wake,
+			waker: Arc::as_ptr(waker) as *const std::ffi::c_void,
+		};
+
+		let mut instance = core::mem::MaybeUninit::<abi::KinectBackendInstance>::uninit();
+		let mut error = [0 as std::ffi::c_char; 512];
+
+		if create(&host, instance.as_mut_ptr(), error.as_mut_ptr(), error.len()) {
+			log::info!("{}: OK! ({name}, {capabilities:?})", backend);
+
+			Ok(Self {
+				name,
+				capabilities,
+				instance: instance.assume_init(),
+				_waker: waker.clone(),
+				_lib: lib,
+			})
+		} else {
+			log::log!(level, "{}: {}", backend, std::ffi::CStr::from_ptr(error.as_ptr()).to_string_lossy());
+			Err(KinectBackendLoadError::Failed)
+		}
+	}
+
+	/// The name the backend reported during the handshake.
+	#[inline]
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	#[inline]
+	pub fn capabilities(&self) -> abi::KinectBackendCapabilities {
+		self.capabilities
+	}
+
+	#[inline]
+	fn ground(&self) -> abi::KinectBackendGround {
+		let mut ground = abi::KinectBackendGround::default();
+		unsafe { (self.instance.ground)(self.instance.backend, &mut ground) };
+		ground
+	}
+}
+impl KinectBackend for DynKinectBackend {
+	fn poll(&mut self) -> Option<KinectBody> {
+		let mut body = abi::KinectBackendBody::default();
+		if unsafe { (self.instance.poll)(self.instance.backend, &mut body) } {
+			Some(KinectBody::from(&body))
+		} else {
+			None
 		}
 	}
+
+	#[inline]
+	fn state(&self) -> KinectBackendState {
+		KinectBackendState::from_raw(unsafe { (self.instance.state)(self.instance.backend) })
+	}
+
+	fn floor_plane(&self) -> Option<KinectFloorPlane> {
+		self.ground().floor_plane()
+	}
+
+	fn sensor_orientation(&self) -> Option<KinectSensorOrientation> {
+		self.ground().sensor_orientation()
+	}
+}
+impl Drop for DynKinectBackend {
+	fn drop(&mut self) {
+		// Must happen before the library is unloaded
+		unsafe { (self.instance.destroy)(self.instance.backend) };
+	}
+}
+
+struct KinectBackendSlot {
+	path: PathBuf,
+	/// The name the backend reported when it was last loaded, or its file name if it never was.
+	name: String,
+	backend: Option<DynKinectBackend>,
+	last_frame: Option<Instant>,
+	/// When to try loading the backend again if it isn't loaded.
+	next_probe: Option<Instant>,
+}
+impl KinectBackendSlot {
+	fn set_backend(&mut self, backend: DynKinectBackend) {
+		self.name = backend.name().to_owned();
+		self.backend = Some(backend);
+		self.next_probe = None;
+	}
+
+	#[inline]
+	fn state(&self) -> KinectBackendState {
+		self.backend.as_ref().map(DynKinectBackend::state).unwrap_or_default()
+	}
 }
 
 pub struct Kinect {
-	backends: Box<[DynKinectBackend]>,
+	backends: Box<[KinectBackendSlot]>,
+	policy: KinectBackendPolicy,
+	active_backend: Option<usize>,
+	reprobe_interval: Duration,
+	reference_frame: KinectReferenceFrame,
+	transform: KinectTransform,
+	validation: Option<KinectValidation>,
+	validator: validation::KinectValidator,
+	inference: Option<KinectInference>,
+	inferrer: inference::KinectInferrer,
+	filters: filter::KinectFilters,
+	calibration: Option<calibration::KinectCalibration>,
+	calibration_state: KinectCalibrationState,
+	profile: Option<KinectBodyProfile>,
+	/// Who [`Kinect::profile`] is applied to. `None` for the selected body, e.g. for a profile loaded from disk.
+	profile_body: Option<KinectBodyId>,
+	calibrations: Vec<KinectCalibrationEvent>,
+	gestures: gesture::KinectGestures,
+	rules: rules::KinectRuleEvaluator,
+	motion: Option<KinectMotion>,
+	motion_tracker: motion::KinectMotionTracker,
+	metrics: metrics::KinectMetricsTracker,
+	bodies: Vec<KinectBody>,
+	selected_body: Option<KinectBodyId>,
+	subscribers: subscription::KinectSubscribers,
+	pub(crate) waker: Arc<abi::HostWaker>,
 }
 impl Kinect {
+	pub const DEFAULT_REPROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+	/// Loads backends from [`KinectBackendSearchPath::from_env`] and selects between them using [`KinectBackendPolicy::from_env`].
+	#[inline]
 	pub fn new() -> Result<Self, std::io::Error> {
-		let mut backends = Vec::new();
+		Self::with_search_path(&KinectBackendSearchPath::from_env(), KinectBackendPolicy::from_env())
+	}
 
-		macro_rules! try_load_backend {
-			($backend:expr) => {
-				for backend in [concat!("garrysmod/lua/bin/", $backend), $backend] {
-					if let Some(backend) = unsafe { DynKinectBackend::load(backend) } {
-						backends.push(backend);
-						break;
-					}
-				}
+	pub fn with_search_path(search_path: &KinectBackendSearchPath, policy: KinectBackendPolicy) -> Result<Self, std::io::Error> {
+		let now = Instant::now();
+		let waker = Arc::new(abi::HostWaker::default());
+
+		let mut backends = Vec::new();
+		for path in search_path.discover() {
+			let mut slot = KinectBackendSlot {
+				name: path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+				path,
+				backend: None,
+				last_frame: None,
+				next_probe: None,
 			};
-		}
 
-		if cfg!(all(windows, target_pointer_width = "64")) {
-			try_load_backend!("rekinect_winsdk_v2_win64.dll");
-			try_load_backend!("rekinect_winsdk_v1_win64.dll");
-		} else if cfg!(all(windows, target_pointer_width = "32")) {
-			try_load_backend!("rekinect_winsdk_v2_win32.dll");
-			try_load_backend!("rekinect_winsdk_v1_win32.dll");
+			match unsafe { DynKinectBackend::load(&slot.path, false, &waker) } {
+				Ok(backend) => slot.set_backend(backend),
+				Err(KinectBackendLoadError::Failed) => slot.next_probe = Some(now + Self::DEFAULT_REPROBE_INTERVAL),
+				Err(KinectBackendLoadError::Incompatible) => continue,
+			}
+
+			backends.push(slot);
 		}
 
 		if !backends.is_empty() {
+			if backends.iter().all(|slot| slot.backend.is_none()) {
+				log::warn!(
+					"No backend could be started yet, will keep trying every {:?}",
+					Self::DEFAULT_REPROBE_INTERVAL
+				);
+			}
+
 			Ok(Kinect {
 				backends: backends.into_boxed_slice(),
+				policy,
+				active_backend: None,
+				reprobe_interval: Self::DEFAULT_REPROBE_INTERVAL,
+				reference_frame: KinectReferenceFrame::default(),
+				transform: KinectTransform::default(),
+				validation: Some(KinectValidation::default()),
+				validator: validation::KinectValidator::default(),
+				inference: None,
+				inferrer: inference::KinectInferrer::default(),
+				filters: filter::KinectFilters::default(),
+				calibration: None,
+				calibration_state: KinectCalibrationState::default(),
+				profile: None,
+				profile_body: None,
+				calibrations: Vec::new(),
+				gestures: gesture::KinectGestures::default(),
+				rules: rules::KinectRuleEvaluator::default(),
+				motion: Some(KinectMotion::default()),
+				motion_tracker: motion::KinectMotionTracker::default(),
+				metrics: metrics::KinectMetricsTracker::default(),
+				bodies: Vec::new(),
+				selected_body: None,
+				subscribers: subscription::KinectSubscribers::new(),
+				waker,
 			})
 		} else {
 			Err(std::io::Error::new(
 				std::io::ErrorKind::Unsupported,
-				"No backend available, did you remember to install one? https://github.com/WilliamVenner/gmcl_rekinect",
+				format!(
+					"No backend available, did you remember to install one? https://github.com/WilliamVenner/gmcl_rekinect (searched {:?})",
+					search_path.entries()
+				),
 			))
 		}
 	}
 
+	/// Drains body updates from every backend and returns the selected body's latest frame if it changed.
+	///
+	/// Only bodies from the active backend are kept, see [`KinectBackendPolicy`].
+	pub fn poll(&mut self) -> Option<KinectFrame> {
+		let now = Instant::now();
+
+		self.reprobe(now);
+
+		let mut frame = self.select_backend(now);
+
+		let alignment = match self.reference_frame {
+			KinectReferenceFrame::Sensor => None,
+			KinectReferenceFrame::Floor => self
+				.active_backend()
+				.and_then(|backend| floor::KinectFloorAlignment::new(backend.floor_plane(), backend.sensor_orientation())),
+		};
+
+		for i in 0..self.backends.len() {
+			while let Some(mut body) = self.backends[i].backend.as_mut().and_then(DynKinectBackend::poll) {
+				self.backends[i].last_frame = Some(now);
+
+				if self.active_backend != Some(i) {
+					continue;
+				}
+
+				if let Some(validation) = &self.validation {
+					self.validator.apply(validation, &mut body);
+				}
+				if let Some(inference) = &self.inference {
+					let (validator, validation, id) = (&self.validator, self.validation.as_ref(), body.id);
+					self.inferrer
+						.apply(inference, &mut body, |joint| validator.bone_length(validation?, id, joint));
+				}
+				if let Some(alignment) = &alignment {
+					alignment.apply(&mut body.frame.skeleton);
+				}
+				self.filters.apply(&self.backends[i].name, &mut body);
+				self.metrics.apply(&body);
+
+				if let Some(calibration) = &mut self.calibration {
+					if self.selected_body.map_or(true, |id| id == body.id) {
+						calibration.sample(body.id, &body.frame.skeleton);
+					}
+				}
+				if let Some(profile) = &self.profile {
+					if self.profile_body.or(self.selected_body).map_or(true, |id| id == body.id) {
+						profile.normalize(&mut body.frame.skeleton);
+					}
+				}
+				if matches!(body.frame.skeleton, KinectSkeleton::Untracked) && self.profile_body == Some(body.id) {
+					// They left, so whoever is selected next is probably the same player coming back
+					self.profile_body = None;
+				}
+
+				let gestures = &mut self.gestures;
+				self.rules.apply(&body, |event| gestures.push(event));
+
+				self.transform.apply(&mut body.frame.skeleton);
+
+				if let Some(motion) = &self.motion {
+					self.motion_tracker.apply(motion, &mut body);
+				}
+
+				let selected = self.selected_body.map_or(true, |id| id == body.id);
+				self.gestures.apply(&body, selected);
+
+				self.subscribers.publish(&body);
+
+				if let Some(update) = self.update_body(body) {
+					frame = Some(update);
+				}
+			}
+		}
+
+		self.finish_calibration(now);
+
+		frame
+	}
+
+	fn finish_calibration(&mut self, now: Instant) {
+		if !self.calibration.as_ref().is_some_and(|calibration| calibration.finished(now)) {
+			return;
+		}
+
+		let Some(calibration) = self.calibration.take() else {
+			return;
+		};
+
+		let body = calibration.body();
+		let profile = calibration.finish();
+
+		match profile {
+			Some(profile) => {
+				log::info!("Calibrated: {profile:?}");
+				self.profile = Some(profile);
+				self.profile_body = body;
+				self.calibration_state = KinectCalibrationState::Calibrated;
+			}
+
+			None => {
+				log::warn!("Calibration failed, the pose wasn't held for long enough");
+				self.calibration_state = KinectCalibrationState::Failed;
+			}
+		}
+
+		self.calibrations.push(KinectCalibrationEvent { body, profile });
+	}
+
+	/// Unloads lost backends and tries loading any backend that isn't loaded again.
+	fn reprobe(&mut self, now: Instant) {
+		for slot in self.backends.iter_mut() {
+			if slot.backend.is_some() {
+				if slot.state() == KinectBackendState::Lost {
+					log::warn!(
+						"{}: Backend was lost, will try loading it again in {:?}",
+						slot.name,
+						self.reprobe_interval
+					);
+
+					slot.backend = None;
+					slot.last_frame = None;
+					slot.next_probe = Some(now + self.reprobe_interval);
+				}
+
+				continue;
+			}
+
+			if slot.next_probe.is_some_and(|next_probe| now >= next_probe) {
+				match unsafe { DynKinectBackend::load(&slot.path, true, &self.waker) } {
+					Ok(backend) => slot.set_backend(backend),
+
+					Err(KinectBackendLoadError::Failed) => slot.next_probe = Some(now + self.reprobe_interval),
+					Err(KinectBackendLoadError::Incompatible) => slot.next_probe = None,
+				}
+			}
+		}
+	}
+
+	/// Applies the policy, returning an untracked frame if switching backends lost the selected body.
+	fn select_backend(&mut self, now: Instant) -> Option<KinectFrame> {
+		let available = |i: &usize| self.backends[*i].state() == KinectBackendState::Ready;
+		let first_available = || (0..self.backends.len()).find(available);
+
+		let active_backend = match &self.policy {
+			KinectBackendPolicy::Priority => first_available(),
+
+			KinectBackendPolicy::Preferred(name) => (0..self.backends.len())
+				.find(|i| self.backends[*i].name == *name)
+				.filter(available)
+				.or_else(first_available),
+
+			KinectBackendPolicy::Failover(timeout) => {
+				let delivering = |i: &usize| available(i) && self.backends[*i].last_frame.is_some_and(|last_frame| now - last_frame < *timeout);
+
+				match self.active_backend {
+					Some(active) if available(&active) && (delivering(&active) || !(0..self.backends.len()).any(|i| delivering(&i))) => Some(active),
+					_ => (0..self.backends.len()).find(delivering).or_else(first_available),
+				}
+			}
+		};
+
+		if active_backend == self.active_backend {
+			return None;
+		}
+
+		match (self.active_backend, active_backend) {
+			(Some(from), Some(to)) => log::info!("Switching backend from {} to {}", self.backends[from].name, self.backends[to].name),
+			(None, Some(to)) => log::info!("Using backend {}", self.backends[to].name),
+			(Some(from), None) => log::warn!(
+				"Backend {} is no longer available, and there is nothing to switch to",
+				self.backends[from].name
+			),
+			(None, None) => unreachable!(),
+		}
+
+		self.active_backend = active_backend;
+		self.validator.reset();
+		self.inferrer.reset();
+		self.filters.reset();
+		self.gestures.reset();
+		self.rules.reset();
+		self.motion_tracker.reset();
+		self.metrics.reset();
+		self.profile_body = None;
+		// Let subscribers know the old backend's bodies are gone
+		for body in std::mem::take(&mut self.bodies) {
+			self.subscribers.publish(&KinectBody {
+				id: body.id,
+				frame: KinectFrame {
+					skeleton: KinectSkeleton::Untracked,
+					..body.frame
+				},
+			});
+		}
+
+		// The new backend's bodies have nothing to do with the old backend's bodies
+		self.selected_body.take().map(|_| KinectFrame::default())
+	}
+
+	fn update_body(&mut self, body: KinectBody) -> Option<KinectFrame> {
+		let tracked = !matches!(body.frame.skeleton, KinectSkeleton::Untracked);
+
+		match (self.bodies.iter().position(|b| b.id == body.id), tracked) {
+			(Some(index), true) => self.bodies[index] = body,
+			(Some(index), false) => {
+				self.bodies.remove(index);
+			}
+			(None, true) => self.bodies.push(body),
+			(None, false) => {}
+		}
+
+		// If nothing is selected, latch onto the first body we see
+		if self.selected_body.is_none() && tracked {
+			self.selected_body = Some(body.id);
+		}
+
+		if self.selected_body != Some(body.id) {
+			return None;
+		}
+
+		if !tracked {
+			self.selected_body = None;
+		}
+
+		Some(body.frame)
+	}
+
+	/// Blocks until a backend has something new for [`Kinect::poll`], or until `timeout` passes. Returns `false` if it timed out.
+	///
+	/// Backends are only re-probed while polling, so pass a timeout if you're relying on that.
+	#[inline]
+	pub fn wait(&self, timeout: Option<Duration>) -> bool {
+		self.waker.wait(timeout)
+	}
+
+	/// Calls `callback` with every body update from the active backend, from inside [`Kinect::poll`].
+	#[inline]
+	pub fn subscribe(&mut self, callback: impl FnMut(&KinectBody) + Send + 'static) -> KinectSubscriptionId {
+		self.subscribers.subscribe(Box::new(callback))
+	}
+
+	/// Queues every body update from the active backend for the returned [`KinectSubscription`], which can be read from any thread.
+	///
+	/// Dropping the [`KinectSubscription`] cancels it.
+	#[inline]
+	pub fn subscribe_channel(&mut self, backpressure: KinectBackpressure) -> KinectSubscription {
+		self.subscribers.subscribe_channel(backpressure)
+	}
+
+	/// Returns `false` if there was no such subscription.
+	#[inline]
+	pub fn unsubscribe(&mut self, id: KinectSubscriptionId) -> bool {
+		self.subscribers.unsubscribe(id)
+	}
+
+	/// Every body currently being tracked, in the order they were first seen.
+	#[inline]
+	pub fn bodies(&self) -> &[KinectBody] {
+		&self.bodies
+	}
+
 	#[inline]
-	pub fn poll(&mut self) -> Option<KinectSkeleton> {
-		self.backends.iter_mut().find_map(|backend| backend.backend.poll())
+	pub fn body(&self, id: KinectBodyId) -> Option<&KinectBody> {
+		self.bodies.iter().find(|body| body.id == id)
+	}
+
+	#[inline]
+	pub fn selected_body(&self) -> Option<KinectBodyId> {
+		self.selected_body
+	}
+
+	/// Selects which body [`Kinect::poll`] reports. Returns the body's latest frame, or `None` if it isn't being tracked.
+	pub fn select_body(&mut self, id: KinectBodyId) -> Option<KinectFrame> {
+		let frame = self.body(id)?.frame;
+		self.selected_body = Some(id);
+		Some(frame)
 	}
 
 	#[inline]
 	pub fn available(&self) -> bool {
-		self.backends.iter().any(|backend| backend.backend.available())
+		self.backends.iter().any(|slot| slot.state() == KinectBackendState::Ready)
+	}
+
+	/// Every loaded backend, from highest to lowest ranked.
+	#[inline]
+	pub fn backends(&self) -> impl Iterator<Item = &DynKinectBackend> {
+		self.backends.iter().filter_map(|slot| slot.backend.as_ref())
+	}
+
+	/// The name and state of every backend that was found, loaded or not, from highest to lowest ranked.
+	///
+	/// Backends that aren't loaded are [`KinectBackendState::NotConnected`].
+	#[inline]
+	pub fn backend_states(&self) -> impl Iterator<Item = (&str, KinectBackendState)> {
+		self.backends.iter().map(|slot| (slot.name.as_str(), slot.state()))
+	}
+
+	/// The backend bodies are currently being taken from.
+	#[inline]
+	pub fn active_backend(&self) -> Option<&DynKinectBackend> {
+		self.active_backend.and_then(|i| self.backends[i].backend.as_ref())
+	}
+
+	#[inline]
+	pub fn reprobe_interval(&self) -> Duration {
+		self.reprobe_interval
+	}
+
+	/// How long to wait before trying to load a backend again after it failed to load or was lost.
+	#[inline]
+	pub fn set_reprobe_interval(&mut self, reprobe_interval: Duration) {
+		self.reprobe_interval = reprobe_interval;
+	}
+
+	/// The active backend's latest estimate of the floor, in sensor space.
+	#[inline]
+	pub fn floor_plane(&self) -> Option<KinectFloorPlane> {
+		self.active_backend()?.floor_plane()
+	}
+
+	/// Which way is up for the active backend's sensor.
+	#[inline]
+	pub fn sensor_orientation(&self) -> Option<KinectSensorOrientation> {
+		self.active_backend()?.sensor_orientation()
+	}
+
+	#[inline]
+	pub fn reference_frame(&self) -> KinectReferenceFrame {
+		self.reference_frame
+	}
+
+	/// Takes effect on the next [`Kinect::poll`], so bodies that aren't updated again stay in the old reference frame.
+	#[inline]
+	pub fn set_reference_frame(&mut self, reference_frame: KinectReferenceFrame) {
+		self.reference_frame = reference_frame;
+	}
+
+	#[inline]
+	pub fn transform(&self) -> &KinectTransform {
+		&self.transform
+	}
+
+	/// Takes effect on the next [`Kinect::poll`], so bodies that aren't updated again stay in the old coordinate space.
+	#[inline]
+	pub fn set_transform(&mut self, transform: KinectTransform) {
+		self.transform = transform;
+	}
+
+	#[inline]
+	pub fn validation(&self) -> Option<&KinectValidation> {
+		self.validation.as_ref()
+	}
+
+	/// Checks every body for glitches before anything else sees it, see [`KinectValidation`]. On by default, pass `None` to turn it off.
+	#[inline]
+	pub fn set_validation(&mut self, validation: Option<KinectValidation>) {
+		self.validation = validation;
+	}
+
+	#[inline]
+	pub fn inference(&self) -> Option<&KinectInference> {
+		self.inference.as_ref()
+	}
+
+	/// Fills in joints the sensor lost from the rest of the body, see [`KinectInference`]. Off by default.
+	#[inline]
+	pub fn set_inference(&mut self, inference: Option<KinectInference>) {
+		self.inference = inference;
+	}
+
+	/// The filters used for backends without their own, see [`Kinect::set_backend_filter`].
+	#[inline]
+	pub fn filter(&self) -> &KinectFilterConfig {
+		&self.filters.default
+	}
+
+	/// Smooths every joint before calibration and the [`KinectTransform`], so filter parameters are in metres. Nothing is filtered by default.
+	///
+	/// Can be changed at any time without losing the filters' state, unless a joint switches to a different kind of filter.
+	#[inline]
+	pub fn set_filter(&mut self, config: KinectFilterConfig) {
+		self.filters.default = config;
+	}
+
+	#[inline]
+	pub fn backend_filter(&self, backend: &str) -> Option<&KinectFilterConfig> {
+		self.filters.backends.get(backend)
+	}
+
+	/// Overrides [`Kinect::set_filter`] for the backend with this [name](DynKinectBackend::name), e.g. because one sensor is noisier than
+	/// another. Pass `None` to go back to the default.
+	pub fn set_backend_filter(&mut self, backend: impl Into<String>, config: Option<KinectFilterConfig>) {
+		let backend = backend.into();
+		match config {
+			Some(config) => self.filters.backends.insert(backend, config),
+			None => self.filters.backends.remove(&backend),
+		};
+	}
+
+	/// Starts measuring the selected body's proportions while it holds `pose` for `duration`, replacing any calibration in progress.
+	/// Without a selected body, the first body to hold `pose` is measured.
+	///
+	/// When it finishes, the new profile is applied to that body's later frames, see [`Kinect::take_calibrations`].
+	#[inline]
+	pub fn calibrate(&mut self, pose: KinectCalibrationPose, duration: Duration) {
+		self.calibration = Some(calibration::KinectCalibration::new(pose, duration, Instant::now()));
+	}
+
+	/// Every calibration that finished since the last call, oldest first.
+	#[inline]
+	pub fn take_calibrations(&mut self) -> Vec<KinectCalibrationEvent> {
+		std::mem::take(&mut self.calibrations)
+	}
+
+	#[inline]
+	pub fn calibration_state(&self) -> KinectCalibrationState {
+		match &self.calibration {
+			Some(calibration) => calibration.state(Instant::now()),
+			None => self.calibration_state,
+		}
+	}
+
+	#[inline]
+	pub fn profile(&self) -> Option<&KinectBodyProfile> {
+		self.profile.as_ref()
+	}
+
+	/// Normalises the selected body's later frames to `profile`, see [`KinectBodyProfile`]. Pass `None` to stop.
+	#[inline]
+	pub fn set_profile(&mut self, profile: Option<KinectBodyProfile>) {
+		self.profile = profile;
+		self.profile_body = None;
+	}
+
+	/// The body's proportions, in the [`KinectTransform`]'s units. `None` until enough of the body has been seen.
+	///
+	/// Measured before calibration normalises the body, see [`Kinect::set_profile`], so these are always the player's own proportions.
+	#[inline]
+	pub fn body_metrics(&self, id: KinectBodyId) -> Option<KinectBodyMetrics> {
+		Some(self.metrics.metrics(id)?.scaled(self.transform.scale))
+	}
+
+	#[inline]
+	pub fn motion(&self) -> Option<&KinectMotion> {
+		self.motion.as_ref()
+	}
+
+	/// Works out every joint's velocity and acceleration, see [`KinectSkeleton::velocity`]. On by default, pass `None` to turn it off.
+	#[inline]
+	pub fn set_motion(&mut self, motion: Option<KinectMotion>) {
+		self.motion = motion;
+	}
+
+	#[inline]
+	pub fn gesture_recognition(&self) -> &KinectGestureRecognition {
+		&self.gestures.recognition
+	}
+
+	#[inline]
+	pub fn set_gesture_recognition(&mut self, recognition: KinectGestureRecognition) {
+		self.gestures.recognition = recognition;
+	}
+
+	#[inline]
+	pub fn gestures(&self) -> &[KinectGestureTemplate] {
+		&self.gestures.templates
+	}
+
+	/// Recognises `template` in every body's motion from now on, replacing any template with the same name. See [`Kinect::take_gestures`].
+	///
+	/// Matching happens after the [`KinectTransform`], so templates should be recorded with the same transform's axes.
+	pub fn add_gesture(&mut self, template: KinectGestureTemplate) {
+		match self.gestures.templates.iter_mut().find(|existing| existing.name == template.name) {
+			Some(existing) => *existing = template,
+			None => self.gestures.templates.push(template),
+		}
+	}
+
+	/// Returns `false` if there was no such template.
+	pub fn remove_gesture(&mut self, name: &str) -> bool {
+		let len = self.gestures.templates.len();
+		self.gestures.templates.retain(|template| template.name != name);
+		self.gestures.templates.len() != len
+	}
+
+	/// Every gesture recognised since the last call, oldest first. Only the latest 64 are kept if nobody takes them.
+	#[inline]
+	pub fn take_gestures(&mut self) -> Vec<KinectGestureEvent> {
+		std::mem::take(&mut self.gestures.events)
+	}
+
+	/// Starts recording the selected body's `joints` for a new [`KinectGestureTemplate`], replacing any recording in progress.
+	#[inline]
+	pub fn record_gesture(&mut self, joints: impl Into<Vec<Joint>>) {
+		self.gestures.recorder = Some(KinectGestureRecorder::new(joints));
+	}
+
+	#[inline]
+	pub fn recording_gesture(&self) -> bool {
+		self.gestures.recorder.is_some()
+	}
+
+	/// Stops recording and returns the template, if enough was recorded. It isn't recognised until it's passed to [`Kinect::add_gesture`].
+	#[inline]
+	pub fn finish_gesture(&mut self, name: impl Into<String>) -> Option<KinectGestureTemplate> {
+		self.gestures.recorder.take()?.finish(name)
+	}
+
+	#[inline]
+	pub fn rules(&self) -> &KinectRules {
+		self.rules.rules()
+	}
+
+	/// Checks every body against `rules` from now on. Poses are reported by [`Kinect::take_poses`], and completed sequences by
+	/// [`Kinect::take_gestures`] with full confidence.
+	///
+	/// Rules are checked before the [`KinectTransform`], so distances are in metres. Any poses being held are released.
+	#[inline]
+	pub fn set_rules(&mut self, rules: KinectRules) {
+		self.rules.set_rules(rules);
+	}
+
+	/// Every pose struck or released since the last call, oldest first. Only the latest 64 are kept if nobody takes them.
+	#[inline]
+	pub fn take_poses(&mut self) -> Vec<KinectPoseEvent> {
+		std::mem::take(&mut self.rules.events)
+	}
+
+	#[inline]
+	pub fn policy(&self) -> &KinectBackendPolicy {
+		&self.policy
+	}
+
+	/// Takes effect on the next [`Kinect::poll`].
+	#[inline]
+	pub fn set_policy(&mut self, policy: KinectBackendPolicy) {
+		self.policy = policy;
+	}
+}
+
+/// Bodies for tests to feed through the pipeline's stages.
+#[cfg(test)]
+pub(crate) mod testing {
+	use super::*;
+
+	/// A tracked skeleton with `joints` where they're given and every other joint at the origin, all in `tracking_state`.
+	pub(crate) fn skeleton(joints: &[(Joint, [f32; 3])], tracking_state: KinectTrackingState) -> KinectSkeleton {
+		let mut bones = [[0.0; 3]; SKELETON_BONE_COUNT];
+		for &(joint, position) in joints {
+			bones[joint.sensor_bone()] = position;
+		}
+
+		KinectSkeleton::Tracked(KinectTrackedSkeleton::from_raw_bones(bones, [tracking_state; SKELETON_BONE_COUNT]))
+	}
+
+	/// Frame `sequence` of body 1, at 30 FPS.
+	pub(crate) fn body(skeleton: KinectSkeleton, sequence: u64) -> KinectBody {
+		KinectBody {
+			id: KinectBodyId(1),
+			frame: KinectFrame {
+				skeleton,
+				timestamp: Duration::from_secs(sequence) / 30,
+				sequence,
+			},
+		}
 	}
 }
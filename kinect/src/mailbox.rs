@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// A lock-free single-value mailbox. Posting replaces whatever hasn't been taken yet, so the reader always gets the newest value and the
+/// writer never blocks.
+pub struct Mailbox<T> {
+	value: AtomicPtr<T>,
+}
+unsafe impl<T: Send> Send for Mailbox<T> {}
+unsafe impl<T: Send> Sync for Mailbox<T> {}
+impl<T> Mailbox<T> {
+	#[inline]
+	pub const fn new() -> Self {
+		Self {
+			value: AtomicPtr::new(core::ptr::null_mut()),
+		}
+	}
+
+	/// Replaces the value in the mailbox, returning the value it replaced if it was never taken.
+	#[inline]
+	pub fn post(&self, value: T) -> Option<T> {
+		Self::unbox(self.value.swap(Box::into_raw(Box::new(value)), Ordering::AcqRel))
+	}
+
+	/// Takes the newest value out of the mailbox, if there is one.
+	#[inline]
+	pub fn take(&self) -> Option<T> {
+		Self::unbox(self.value.swap(core::ptr::null_mut(), Ordering::AcqRel))
+	}
+
+	#[inline]
+	fn unbox(ptr: *mut T) -> Option<T> {
+		if !ptr.is_null() {
+			// Swapping the pointer out of the mailbox transferred ownership of it to us
+			Some(*unsafe { Box::from_raw(ptr) })
+		} else {
+			None
+		}
+	}
+}
+impl<T> Default for Mailbox<T> {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl<T> Drop for Mailbox<T> {
+	#[inline]
+	fn drop(&mut self) {
+		self.take();
+	}
+}
+impl<T> std::fmt::Debug for Mailbox<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Mailbox")
+			.field("full", &!self.value.load(Ordering::Acquire).is_null())
+			.finish()
+	}
+}
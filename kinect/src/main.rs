@@ -33,11 +33,18 @@ fn main() -> Result<(), std::io::Error> {
 
 	{
 		let mut kinect = kinect::Kinect::new().unwrap();
+
+		kinect.subscribe(|body| {
+			log::debug!("body {:?} updated (sequence {})", body.id, body.frame.sequence);
+		});
+
 		while !SHUTDOWN.load(std::sync::atomic::Ordering::Acquire) {
+			// Wake up now and then so that we notice CTRL+C and backends get re-probed
+			kinect.wait(Some(std::time::Duration::from_millis(100)));
+
 			if let Some(update) = kinect.poll() {
 				println!("{:#?}", update);
 			}
-			std::thread::sleep(std::time::Duration::from_millis(50));
 		}
 	}
 
@@ -0,0 +1,86 @@
+//! Just enough vector maths for processing skeletons. Quaternions are `[x, y, z, w]`.
+
+pub(crate) type Vec3 = [f32; 3];
+pub(crate) type Quat = [f32; 4];
+
+#[inline]
+pub(crate) fn add(a: Vec3, b: Vec3) -> Vec3 {
+	[a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+#[inline]
+pub(crate) fn sub(a: Vec3, b: Vec3) -> Vec3 {
+	[a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+#[inline]
+pub(crate) fn scale(a: Vec3, scale: f32) -> Vec3 {
+	[a[0] * scale, a[1] * scale, a[2] * scale]
+}
+
+/// `a` at 0, `b` at 1.
+#[inline]
+pub(crate) fn lerp(a: Vec3, b: Vec3, t: f32) -> Vec3 {
+	add(a, scale(sub(b, a), t))
+}
+
+#[inline]
+pub(crate) fn dot(a: Vec3, b: Vec3) -> f32 {
+	a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[inline]
+pub(crate) fn cross(a: Vec3, b: Vec3) -> Vec3 {
+	[a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+#[inline]
+pub(crate) fn length(a: Vec3) -> f32 {
+	dot(a, a).sqrt()
+}
+
+/// Returns `None` for (nearly) zero vectors.
+#[inline]
+pub(crate) fn normalize(a: Vec3) -> Option<Vec3> {
+	let length = length(a);
+	if length > f32::EPSILON {
+		Some(scale(a, 1.0 / length))
+	} else {
+		None
+	}
+}
+
+#[inline]
+pub(crate) fn quat_mul(a: Quat, b: Quat) -> Quat {
+	[
+		a[3] * b[0] + a[0] * b[3] + a[1] * b[2] - a[2] * b[1],
+		a[3] * b[1] - a[0] * b[2] + a[1] * b[3] + a[2] * b[0],
+		a[3] * b[2] + a[0] * b[1] - a[1] * b[0] + a[2] * b[3],
+		a[3] * b[3] - a[0] * b[0] - a[1] * b[1] - a[2] * b[2],
+	]
+}
+
+#[inline]
+pub(crate) fn quat_rotate(q: Quat, v: Vec3) -> Vec3 {
+	let axis = [q[0], q[1], q[2]];
+	let t = scale(cross(axis, v), 2.0);
+	add(add(v, scale(t, q[3])), cross(axis, t))
+}
+
+/// The shortest rotation taking the direction `from` to the direction `to`. Both must be normalized.
+pub(crate) fn quat_from_to(from: Vec3, to: Vec3) -> Quat {
+	let d = dot(from, to);
+
+	if d < -1.0 + 1e-6 {
+		// Opposite directions, so rotate half a turn about any perpendicular axis
+		let axis = normalize(cross([1.0, 0.0, 0.0], from))
+			.or_else(|| normalize(cross([0.0, 1.0, 0.0], from)))
+			.unwrap_or([0.0, 0.0, 1.0]);
+		return [axis[0], axis[1], axis[2], 0.0];
+	}
+
+	let axis = cross(from, to);
+	let q = [axis[0], axis[1], axis[2], 1.0 + d];
+	let length = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+	[q[0] / length, q[1] / length, q[2] / length, q[3] / length]
+}
@@ -0,0 +1,202 @@
+use crate::{math, Joint, KinectBody, KinectBodyId, KinectSkeleton, KinectTrackingState};
+use std::collections::VecDeque;
+
+/// How far the top of the head is above the head joint, which the sensor puts in the middle of the head.
+const HEAD_TOP: f32 = 0.12;
+
+/// How far the soles of the feet are below the ankles, plus how far the hip joints are below the hips.
+const SOLES: f32 = 0.12;
+
+/// How far the fingertips are from the hand joint, which the sensor puts in the middle of the palm.
+const FINGERTIPS: f32 = 0.1;
+
+/// Every segment is the median of this many of its latest measurements, so the estimates keep up if the player changes.
+const WINDOW: usize = 300;
+
+/// Every segment needs at least this many measurements before anything is reported.
+const MIN_SAMPLES: usize = 15;
+
+/// A body's proportions, estimated from the medians of its latest measured segments, see [`Kinect::body_metrics`](crate::Kinect::body_metrics).
+///
+/// Lengths are in the [`KinectTransform`](crate::KinectTransform)'s units. The sensor can't see the top of the head, the soles of the feet
+/// or the fingertips, so `height` and `arm_span` add typical adult proportions for those.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct KinectBodyMetrics {
+	/// Standing up straight, from the soles of the feet to the top of the head.
+	pub height: f32,
+	/// From fingertip to fingertip with both arms held out to the sides.
+	pub arm_span: f32,
+	pub shoulder_width: f32,
+	/// From the shoulder to the elbow.
+	pub upper_arm: f32,
+	/// From the elbow to the wrist.
+	pub forearm: f32,
+	/// From the wrist to the middle of the palm.
+	pub hand: f32,
+	/// From the hip to the knee.
+	pub thigh: f32,
+	/// From the knee to the ankle.
+	pub shin: f32,
+	/// From the hips to the base of the neck.
+	pub torso: f32,
+	/// From the base of the neck to the middle of the head.
+	pub neck: f32,
+	/// How many frames the estimates are based on.
+	pub frames: u32,
+}
+impl KinectBodyMetrics {
+	/// Multiplies every length by `scale`, e.g. to take them out of metres.
+	pub fn scaled(self, scale: f32) -> Self {
+		Self {
+			height: self.height * scale,
+			arm_span: self.arm_span * scale,
+			shoulder_width: self.shoulder_width * scale,
+			upper_arm: self.upper_arm * scale,
+			forearm: self.forearm * scale,
+			hand: self.hand * scale,
+			thigh: self.thigh * scale,
+			shin: self.shin * scale,
+			torso: self.torso * scale,
+			neck: self.neck * scale,
+			frames: self.frames,
+		}
+	}
+}
+
+/// The latest measurements of one segment.
+#[derive(Default)]
+struct Samples(VecDeque<f32>);
+impl Samples {
+	fn push(&mut self, sample: Option<f32>) {
+		let Some(sample) = sample.filter(|sample| sample.is_finite() && *sample > 0.0) else {
+			return;
+		};
+
+		if self.0.len() >= WINDOW {
+			self.0.pop_front();
+		}
+		self.0.push_back(sample);
+	}
+
+	fn median(&self) -> Option<f32> {
+		if self.0.len() < MIN_SAMPLES {
+			return None;
+		}
+
+		let mut sorted = self.0.iter().copied().collect::<Vec<_>>();
+		let middle = sorted.len() / 2;
+		Some(*sorted.select_nth_unstable_by(middle, f32::total_cmp).1)
+	}
+}
+
+#[derive(Default)]
+struct BodyMetrics {
+	frames: u32,
+	shoulder_width: Samples,
+	upper_arm: Samples,
+	forearm: Samples,
+	hand: Samples,
+	thigh: Samples,
+	shin: Samples,
+	torso: Samples,
+	neck: Samples,
+}
+impl BodyMetrics {
+	fn sample(&mut self, skeleton: &KinectSkeleton) {
+		self.frames += 1;
+
+		// Only trust what the sensor actually saw
+		let length = |a: Joint, b: Joint| {
+			if skeleton.tracking_state(a) != KinectTrackingState::Tracked || skeleton.tracking_state(b) != KinectTrackingState::Tracked {
+				return None;
+			}
+			Some(math::length(math::sub(*skeleton.bone(a)?, *skeleton.bone(b)?)))
+		};
+
+		self.shoulder_width.push(length(Joint::ShoulderLeft, Joint::ShoulderRight));
+
+		for [shoulder, elbow, wrist, hand] in [
+			[Joint::ShoulderLeft, Joint::ElbowLeft, Joint::WristLeft, Joint::HandLeft],
+			[Joint::ShoulderRight, Joint::ElbowRight, Joint::WristRight, Joint::HandRight],
+		] {
+			self.upper_arm.push(length(shoulder, elbow));
+			self.forearm.push(length(elbow, wrist));
+			self.hand.push(length(wrist, hand));
+		}
+
+		for [hip, knee, ankle] in [
+			[Joint::HipLeft, Joint::KneeLeft, Joint::AnkleLeft],
+			[Joint::HipRight, Joint::KneeRight, Joint::AnkleRight],
+		] {
+			self.thigh.push(length(hip, knee));
+			self.shin.push(length(knee, ankle));
+		}
+
+		let torso = length(Joint::HipCenter, Joint::Spine).zip(length(Joint::Spine, Joint::ShoulderCenter));
+		self.torso.push(torso.map(|(lower, upper)| lower + upper));
+		self.neck.push(length(Joint::ShoulderCenter, Joint::Head));
+	}
+
+	fn metrics(&self) -> Option<KinectBodyMetrics> {
+		let (shoulder_width, upper_arm, forearm, hand) = (
+			self.shoulder_width.median()?,
+			self.upper_arm.median()?,
+			self.forearm.median()?,
+			self.hand.median()?,
+		);
+		let (thigh, shin, torso, neck) = (self.thigh.median()?, self.shin.median()?, self.torso.median()?, self.neck.median()?);
+
+		Some(KinectBodyMetrics {
+			height: SOLES + shin + thigh + torso + neck + HEAD_TOP,
+			arm_span: shoulder_width + 2.0 * (upper_arm + forearm + hand + FINGERTIPS),
+			shoulder_width,
+			upper_arm,
+			forearm,
+			hand,
+			thigh,
+			shin,
+			torso,
+			neck,
+			frames: self.frames,
+		})
+	}
+}
+
+/// Every body's measurements.
+#[derive(Default)]
+pub(crate) struct KinectMetricsTracker {
+	bodies: Vec<(KinectBodyId, Box<BodyMetrics>)>,
+}
+impl KinectMetricsTracker {
+	pub(crate) fn apply(&mut self, body: &KinectBody) {
+		let index = self.bodies.iter().position(|(id, _)| *id == body.id);
+
+		if matches!(body.frame.skeleton, KinectSkeleton::Untracked) {
+			if let Some(index) = index {
+				self.bodies.swap_remove(index);
+			}
+			return;
+		}
+
+		let state = match index {
+			Some(index) => &mut self.bodies[index].1,
+			None => {
+				self.bodies.push((body.id, Box::default()));
+				&mut self.bodies.last_mut().unwrap().1
+			}
+		};
+
+		state.sample(&body.frame.skeleton);
+	}
+
+	/// In metres.
+	pub(crate) fn metrics(&self, id: KinectBodyId) -> Option<KinectBodyMetrics> {
+		self.bodies.iter().find(|(body, _)| *body == id)?.1.metrics()
+	}
+
+	/// Forgets every body, e.g. when switching backends.
+	#[inline]
+	pub(crate) fn reset(&mut self) {
+		self.bodies.clear();
+	}
+}
@@ -0,0 +1,160 @@
+use crate::{
+	filter,
+	math::{self, Vec3},
+	Joint, KinectBody, KinectBodyId, KinectSkeleton, KinectTrackingState,
+};
+use std::time::Duration;
+
+/// How [`Kinect`](crate::Kinect) works out each joint's velocity and acceleration from the frame timestamps.
+///
+/// Both are differentiated from the final joint positions, so they're in the [`KinectTransform`](crate::KinectTransform)'s units per
+/// second, and low pass filtered since differentiating amplifies jitter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectMotion {
+	/// The velocity filter's cutoff frequency, in Hz. Lower is smoother but lags more.
+	pub velocity_cutoff: f32,
+	/// The acceleration filter's cutoff frequency, in Hz.
+	pub acceleration_cutoff: f32,
+}
+impl Default for KinectMotion {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			velocity_cutoff: 8.0,
+			acceleration_cutoff: 4.0,
+		}
+	}
+}
+impl KinectMotion {
+	#[inline]
+	pub const fn with_velocity_cutoff(mut self, velocity_cutoff: f32) -> Self {
+		self.velocity_cutoff = velocity_cutoff;
+		self
+	}
+
+	#[inline]
+	pub const fn with_acceleration_cutoff(mut self, acceleration_cutoff: f32) -> Self {
+		self.acceleration_cutoff = acceleration_cutoff;
+		self
+	}
+}
+
+/// Assume 30 FPS if the backend doesn't have sensible timestamps.
+const FALLBACK_FRAME_TIME: f32 = 1.0 / 30.0;
+
+/// After a gap this long, start over rather than report one huge jump.
+const MAX_FRAME_TIME: Duration = Duration::from_millis(500);
+
+struct BodyMotion {
+	timestamp: Duration,
+	/// `None` while the joint isn't tracked.
+	positions: [Option<Vec3>; Joint::COUNT],
+	velocities: [Vec3; Joint::COUNT],
+	accelerations: [Vec3; Joint::COUNT],
+}
+impl BodyMotion {
+	fn new(timestamp: Duration) -> Self {
+		Self {
+			timestamp,
+			positions: [None; Joint::COUNT],
+			velocities: [[0.0; 3]; Joint::COUNT],
+			accelerations: [[0.0; 3]; Joint::COUNT],
+		}
+	}
+
+	fn apply(&mut self, motion: &KinectMotion, skeleton: &mut KinectSkeleton, timestamp: Duration) {
+		let dt = match timestamp.checked_sub(self.timestamp) {
+			Some(dt) if dt > MAX_FRAME_TIME => {
+				*self = Self::new(timestamp);
+				FALLBACK_FRAME_TIME
+			}
+			Some(dt) if !dt.is_zero() => dt.as_secs_f32(),
+			_ => FALLBACK_FRAME_TIME,
+		};
+		self.timestamp = timestamp;
+
+		let (velocity_factor, acceleration_factor) = (
+			filter::smoothing_factor(motion.velocity_cutoff, dt),
+			filter::smoothing_factor(motion.acceleration_cutoff, dt),
+		);
+
+		for joint in Joint::ALL {
+			let index = joint.sensor_bone();
+
+			let position = skeleton
+				.bone(joint)
+				.copied()
+				.filter(|_| skeleton.tracking_state(joint) != KinectTrackingState::NotTracked);
+
+			let (velocity, acceleration) = match (position, self.positions[index]) {
+				(Some(position), Some(previous)) => {
+					let velocity = math::lerp(
+						self.velocities[index],
+						math::scale(math::sub(position, previous), 1.0 / dt),
+						velocity_factor,
+					);
+
+					let acceleration = math::lerp(
+						self.accelerations[index],
+						math::scale(math::sub(velocity, self.velocities[index]), 1.0 / dt),
+						acceleration_factor,
+					);
+
+					(velocity, acceleration)
+				}
+
+				_ => ([0.0; 3], [0.0; 3]),
+			};
+
+			self.positions[index] = position;
+			self.velocities[index] = velocity;
+			self.accelerations[index] = acceleration;
+		}
+
+		let (velocities, accelerations) = (&self.velocities, &self.accelerations);
+		let velocity = |joint: Joint| velocities[joint.sensor_bone()];
+		let acceleration = |joint: Joint| accelerations[joint.sensor_bone()];
+
+		if let Some(tracked) = skeleton.tracked_mut() {
+			*tracked = tracked.with_motion(Joint::SKELETON.map(velocity), Joint::SKELETON.map(acceleration));
+		}
+
+		if let Some(extended) = skeleton.extended_mut() {
+			*extended = extended.with_motion(Joint::EXTENDED.map(velocity), Joint::EXTENDED.map(acceleration));
+		}
+	}
+}
+
+/// Every body's recent motion.
+#[derive(Default)]
+pub(crate) struct KinectMotionTracker {
+	bodies: Vec<(KinectBodyId, Box<BodyMotion>)>,
+}
+impl KinectMotionTracker {
+	pub(crate) fn apply(&mut self, motion: &KinectMotion, body: &mut KinectBody) {
+		let index = self.bodies.iter().position(|(id, _)| *id == body.id);
+
+		if matches!(body.frame.skeleton, KinectSkeleton::Untracked) {
+			if let Some(index) = index {
+				self.bodies.swap_remove(index);
+			}
+			return;
+		}
+
+		let state = match index {
+			Some(index) => &mut self.bodies[index].1,
+			None => {
+				self.bodies.push((body.id, Box::new(BodyMotion::new(body.frame.timestamp))));
+				&mut self.bodies.last_mut().unwrap().1
+			}
+		};
+
+		state.apply(motion, &mut body.frame.skeleton, body.frame.timestamp);
+	}
+
+	/// Forgets every body, e.g. when switching backends.
+	#[inline]
+	pub(crate) fn reset(&mut self) {
+		self.bodies.clear();
+	}
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+/// Environment variable naming the backend [`KinectBackendPolicy::from_env`] should prefer.
+pub const BACKEND_PREFERRED_ENV: &str = "GMCL_REKINECT_BACKEND";
+
+/// Decides which loaded backend [`Kinect`](crate::Kinect) takes bodies from. Bodies from every other backend are discarded.
+///
+/// Backends are ranked in the order they were loaded, see [`KinectBackendSearchPath`](crate::KinectBackendSearchPath).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KinectBackendPolicy {
+	/// Use the highest ranked available backend.
+	Priority,
+
+	/// Use the backend with this name whenever it is available, otherwise behave like [`KinectBackendPolicy::Priority`].
+	Preferred(String),
+
+	/// Stick with the active backend until it stops delivering frames for this long while another available backend is still delivering them,
+	/// then switch to the highest ranked backend that is.
+	Failover(Duration),
+}
+impl Default for KinectBackendPolicy {
+	#[inline]
+	fn default() -> Self {
+		Self::Failover(Duration::from_secs(1))
+	}
+}
+impl KinectBackendPolicy {
+	/// [`KinectBackendPolicy::Preferred`] if [`BACKEND_PREFERRED_ENV`] is set, otherwise the default policy.
+	pub fn from_env() -> Self {
+		match std::env::var(BACKEND_PREFERRED_ENV) {
+			Ok(name) if !name.is_empty() => Self::Preferred(name),
+			_ => Self::default(),
+		}
+	}
+}
@@ -0,0 +1,338 @@
+use crate::{
+	math, Joint, KinectSkeleton, KinectTrackedExtendedSkeleton, KinectTrackedSkeleton, KinectTrackingState, EXTENDED_SKELETON_BONE_COUNT,
+	SKELETON_BONE_COUNT,
+};
+
+/// Where a [`KinectRetargetRule`] takes its position from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KinectRetargetSource {
+	/// A source joint, by index.
+	Joint(usize),
+	/// The average of several source joints, by index. Only as well tracked as the worst of them.
+	Average(&'static [usize]),
+}
+
+/// How one [`Joint`] is built from a source skeleton.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectRetargetRule {
+	pub position: KinectRetargetSource,
+	/// Added to the position, in metres in sensor space.
+	pub offset: [f32; 3],
+	/// The source joint the orientation is taken from. The identity if `None`.
+	pub orientation: Option<usize>,
+}
+impl KinectRetargetRule {
+	/// Takes everything from one source joint.
+	#[inline]
+	pub const fn copy(source: usize) -> Self {
+		Self {
+			position: KinectRetargetSource::Joint(source),
+			offset: [0.0; 3],
+			orientation: Some(source),
+		}
+	}
+
+	/// Averages the positions of several source joints. Use [`with_orientation`](Self::with_orientation) to give it an orientation.
+	#[inline]
+	pub const fn average(sources: &'static [usize]) -> Self {
+		Self {
+			position: KinectRetargetSource::Average(sources),
+			offset: [0.0; 3],
+			orientation: None,
+		}
+	}
+
+	#[inline]
+	pub const fn with_offset(mut self, offset: [f32; 3]) -> Self {
+		self.offset = offset;
+		self
+	}
+
+	#[inline]
+	pub const fn with_orientation(mut self, source: usize) -> Self {
+		self.orientation = Some(source);
+		self
+	}
+
+	fn sources(&self) -> &[usize] {
+		match &self.position {
+			KinectRetargetSource::Joint(source) => std::slice::from_ref(source),
+			KinectRetargetSource::Average(sources) => sources,
+		}
+	}
+}
+
+/// Maps another skeleton definition onto [`Joint`]s, one [`KinectRetargetRule`] per joint.
+///
+/// Every joint in [`Joint::SKELETON`] needs a rule. The extended joints are only produced if every one of them has a rule too.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectRetargetTable {
+	pub name: &'static str,
+	/// The source skeleton's joints, in the order their data comes in.
+	pub source_joints: &'static [&'static str],
+	pub rules: [Option<KinectRetargetRule>; Joint::COUNT],
+}
+impl KinectRetargetTable {
+	/// The Xbox 360 Kinect's 20 joints, which are exactly [`Joint::SKELETON`].
+	pub const KINECT_V1: Self = {
+		let mut table = Self::new("Kinect v1", &KINECT_V1_JOINTS);
+		let mut i = 0;
+		while i < SKELETON_BONE_COUNT {
+			table = table.with_rule(Joint::SKELETON[i], KinectRetargetRule::copy(i));
+			i += 1;
+		}
+		table
+	};
+
+	/// The Xbox One Kinect's 25 joints. It has no hip or shoulder centre, so those are averaged from the hips and shoulders.
+	pub const KINECT_V2: Self = Self::new("Kinect v2", &KINECT_V2_JOINTS)
+		.with_rule(
+			Joint::HipCenter,
+			KinectRetargetRule::average(&[v2::HIP_LEFT, v2::HIP_RIGHT]).with_orientation(v2::SPINE_BASE),
+		)
+		.with_rule(Joint::Spine, KinectRetargetRule::copy(v2::SPINE_MID))
+		.with_rule(
+			Joint::ShoulderCenter,
+			KinectRetargetRule::average(&[v2::SHOULDER_LEFT, v2::SHOULDER_RIGHT]).with_orientation(v2::SPINE_SHOULDER),
+		)
+		.with_rule(Joint::Head, KinectRetargetRule::copy(v2::HEAD))
+		.with_rule(Joint::ShoulderLeft, KinectRetargetRule::copy(v2::SHOULDER_LEFT))
+		.with_rule(Joint::ElbowLeft, KinectRetargetRule::copy(v2::ELBOW_LEFT))
+		.with_rule(Joint::WristLeft, KinectRetargetRule::copy(v2::WRIST_LEFT))
+		.with_rule(Joint::HandLeft, KinectRetargetRule::copy(v2::HAND_LEFT))
+		.with_rule(Joint::ShoulderRight, KinectRetargetRule::copy(v2::SHOULDER_RIGHT))
+		.with_rule(Joint::ElbowRight, KinectRetargetRule::copy(v2::ELBOW_RIGHT))
+		.with_rule(Joint::WristRight, KinectRetargetRule::copy(v2::WRIST_RIGHT))
+		.with_rule(Joint::HandRight, KinectRetargetRule::copy(v2::HAND_RIGHT))
+		.with_rule(Joint::HipLeft, KinectRetargetRule::copy(v2::HIP_LEFT))
+		.with_rule(Joint::KneeLeft, KinectRetargetRule::copy(v2::KNEE_LEFT))
+		.with_rule(Joint::AnkleLeft, KinectRetargetRule::copy(v2::ANKLE_LEFT))
+		.with_rule(Joint::FootLeft, KinectRetargetRule::copy(v2::FOOT_LEFT))
+		.with_rule(Joint::HipRight, KinectRetargetRule::copy(v2::HIP_RIGHT))
+		.with_rule(Joint::KneeRight, KinectRetargetRule::copy(v2::KNEE_RIGHT))
+		.with_rule(Joint::AnkleRight, KinectRetargetRule::copy(v2::ANKLE_RIGHT))
+		.with_rule(Joint::FootRight, KinectRetargetRule::copy(v2::FOOT_RIGHT))
+		.with_rule(Joint::SpineBase, KinectRetargetRule::copy(v2::SPINE_BASE))
+		.with_rule(Joint::Neck, KinectRetargetRule::copy(v2::NECK))
+		.with_rule(Joint::SpineShoulder, KinectRetargetRule::copy(v2::SPINE_SHOULDER))
+		.with_rule(Joint::HandTipLeft, KinectRetargetRule::copy(v2::HAND_TIP_LEFT))
+		.with_rule(Joint::ThumbLeft, KinectRetargetRule::copy(v2::THUMB_LEFT))
+		.with_rule(Joint::HandTipRight, KinectRetargetRule::copy(v2::HAND_TIP_RIGHT))
+		.with_rule(Joint::ThumbRight, KinectRetargetRule::copy(v2::THUMB_RIGHT));
+
+	/// A table with no rules yet.
+	#[inline]
+	pub const fn new(name: &'static str, source_joints: &'static [&'static str]) -> Self {
+		Self {
+			name,
+			source_joints,
+			rules: [None; Joint::COUNT],
+		}
+	}
+
+	#[inline]
+	pub const fn with_rule(mut self, joint: Joint, rule: KinectRetargetRule) -> Self {
+		self.rules[joint.sensor_bone()] = Some(rule);
+		self
+	}
+
+	#[inline]
+	pub fn rule(&self, joint: Joint) -> Option<&KinectRetargetRule> {
+		self.rules[joint.sensor_bone()].as_ref()
+	}
+
+	/// Builds a skeleton from one frame of the source skeleton, indexed like [`source_joints`](Self::source_joints).
+	///
+	/// Returns `None` if there's too little data or a joint in [`Joint::SKELETON`] has no rule.
+	pub fn retarget(&self, bones: &[[f32; 3]], tracking_states: &[KinectTrackingState], orientations: Option<&[[f32; 4]]>) -> Option<KinectSkeleton> {
+		let count = self.source_joints.len();
+		if bones.len() < count || tracking_states.len() < count || orientations.is_some_and(|orientations| orientations.len() < count) {
+			return None;
+		}
+
+		let retarget_joint = |joint: Joint| -> Option<([f32; 3], KinectTrackingState, [f32; 4])> {
+			let rule = self.rule(joint)?;
+			let sources = rule.sources();
+			if sources.is_empty() || sources.iter().any(|source| *source >= count) {
+				return None;
+			}
+
+			let sum = sources.iter().fold([0.0; 3], |sum, source| math::add(sum, bones[*source]));
+			let position = math::add(math::scale(sum, 1.0 / sources.len() as f32), rule.offset);
+
+			let tracking_state = sources.iter().map(|source| tracking_states[*source]).min().unwrap_or_default();
+
+			let orientation = match (orientations, rule.orientation) {
+				(Some(orientations), Some(source)) => *orientations.get(source)?,
+				_ => [0.0, 0.0, 0.0, 1.0],
+			};
+
+			Some((position, tracking_state, orientation))
+		};
+
+		let mut skeleton = [([0.0; 3], KinectTrackingState::NotTracked, [0.0; 4]); SKELETON_BONE_COUNT];
+		for (joint, data) in Joint::SKELETON.into_iter().zip(skeleton.iter_mut()) {
+			*data = retarget_joint(joint)?;
+		}
+
+		let mut tracked = KinectTrackedSkeleton::from_raw_bones(skeleton.map(|(bone, _, _)| bone), skeleton.map(|(_, state, _)| state));
+		if orientations.is_some() {
+			tracked = tracked.with_orientations(skeleton.map(|(_, _, orientation)| orientation));
+		}
+
+		let mut extended = [([0.0; 3], KinectTrackingState::NotTracked, [0.0; 4]); EXTENDED_SKELETON_BONE_COUNT];
+		for (joint, data) in Joint::EXTENDED.into_iter().zip(extended.iter_mut()) {
+			match retarget_joint(joint) {
+				Some(joint) => *data = joint,
+				None => return Some(KinectSkeleton::Tracked(tracked)),
+			}
+		}
+
+		let mut extended_skeleton =
+			KinectTrackedExtendedSkeleton::from_raw_bones(extended.map(|(bone, _, _)| bone), extended.map(|(_, state, _)| state));
+		if orientations.is_some() {
+			extended_skeleton = extended_skeleton.with_orientations(extended.map(|(_, _, orientation)| orientation));
+		}
+
+		Some(KinectSkeleton::TrackedExtended(tracked, extended_skeleton))
+	}
+}
+
+const KINECT_V1_JOINTS: [&str; SKELETON_BONE_COUNT] = {
+	let mut names = [""; SKELETON_BONE_COUNT];
+	let mut i = 0;
+	while i < SKELETON_BONE_COUNT {
+		names[i] = Joint::SKELETON[i].name();
+		i += 1;
+	}
+	names
+};
+
+/// `JointType` in the Kinect for Windows SDK 2.0.
+mod v2 {
+	pub const SPINE_BASE: usize = 0;
+	pub const SPINE_MID: usize = 1;
+	pub const NECK: usize = 2;
+	pub const HEAD: usize = 3;
+	pub const SHOULDER_LEFT: usize = 4;
+	pub const ELBOW_LEFT: usize = 5;
+	pub const WRIST_LEFT: usize = 6;
+	pub const HAND_LEFT: usize = 7;
+	pub const SHOULDER_RIGHT: usize = 8;
+	pub const ELBOW_RIGHT: usize = 9;
+	pub const WRIST_RIGHT: usize = 10;
+	pub const HAND_RIGHT: usize = 11;
+	pub const HIP_LEFT: usize = 12;
+	pub const KNEE_LEFT: usize = 13;
+	pub const ANKLE_LEFT: usize = 14;
+	pub const FOOT_LEFT: usize = 15;
+	pub const HIP_RIGHT: usize = 16;
+	pub const KNEE_RIGHT: usize = 17;
+	pub const ANKLE_RIGHT: usize = 18;
+	pub const FOOT_RIGHT: usize = 19;
+	pub const SPINE_SHOULDER: usize = 20;
+	pub const HAND_TIP_LEFT: usize = 21;
+	pub const THUMB_LEFT: usize = 22;
+	pub const HAND_TIP_RIGHT: usize = 23;
+	pub const THUMB_RIGHT: usize = 24;
+}
+
+const KINECT_V2_JOINTS: [&str; 25] = [
+	"SPINE_BASE",
+	"SPINE_MID",
+	"NECK",
+	"HEAD",
+	"SHOULDER_LEFT",
+	"ELBOW_LEFT",
+	"WRIST_LEFT",
+	"HAND_LEFT",
+	"SHOULDER_RIGHT",
+	"ELBOW_RIGHT",
+	"WRIST_RIGHT",
+	"HAND_RIGHT",
+	"HIP_LEFT",
+	"KNEE_LEFT",
+	"ANKLE_LEFT",
+	"FOOT_LEFT",
+	"HIP_RIGHT",
+	"KNEE_RIGHT",
+	"ANKLE_RIGHT",
+	"FOOT_RIGHT",
+	"SPINE_SHOULDER",
+	"HAND_TIP_LEFT",
+	"THUMB_LEFT",
+	"HAND_TIP_RIGHT",
+	"THUMB_RIGHT",
+];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Every v2 joint at a distinct position, fully tracked.
+	fn v2_frame() -> ([[f32; 3]; 25], [KinectTrackingState; 25]) {
+		(
+			std::array::from_fn(|i| [i as f32, i as f32 * 2.0, i as f32 * 3.0]),
+			[KinectTrackingState::Tracked; 25],
+		)
+	}
+
+	#[test]
+	fn v2_averages_centres() {
+		let (bones, states) = v2_frame();
+		let skeleton = KinectRetargetTable::KINECT_V2.retarget(&bones, &states, None).unwrap();
+
+		let average = |a: usize, b: usize| math::scale(math::add(bones[a], bones[b]), 0.5);
+		assert_eq!(skeleton.bone(Joint::HipCenter), Some(&average(v2::HIP_LEFT, v2::HIP_RIGHT)));
+		assert_eq!(
+			skeleton.bone(Joint::ShoulderCenter),
+			Some(&average(v2::SHOULDER_LEFT, v2::SHOULDER_RIGHT))
+		);
+		assert_eq!(skeleton.bone(Joint::Head), Some(&bones[v2::HEAD]));
+	}
+
+	#[test]
+	fn v2_average_is_as_tracked_as_its_worst_source() {
+		let (bones, mut states) = v2_frame();
+		states[v2::HIP_LEFT] = KinectTrackingState::Inferred;
+		states[v2::SHOULDER_RIGHT] = KinectTrackingState::NotTracked;
+
+		let skeleton = KinectRetargetTable::KINECT_V2.retarget(&bones, &states, None).unwrap();
+		assert_eq!(skeleton.tracking_state(Joint::HipCenter), KinectTrackingState::Inferred);
+		assert_eq!(skeleton.tracking_state(Joint::ShoulderCenter), KinectTrackingState::NotTracked);
+		assert_eq!(skeleton.tracking_state(Joint::Spine), KinectTrackingState::Tracked);
+	}
+
+	#[test]
+	fn offsets_are_added() {
+		let (bones, states) = v2_frame();
+		let table = KinectRetargetTable::KINECT_V2.with_rule(Joint::Head, KinectRetargetRule::copy(v2::HEAD).with_offset([0.0, 0.1, 0.0]));
+
+		let skeleton = table.retarget(&bones, &states, None).unwrap();
+		assert_eq!(skeleton.bone(Joint::Head), Some(&math::add(bones[v2::HEAD], [0.0, 0.1, 0.0])));
+	}
+
+	#[test]
+	fn extended_joints_need_every_rule() {
+		let (bones, states) = v2_frame();
+		let skeleton = KinectRetargetTable::KINECT_V2.retarget(&bones, &states, None).unwrap();
+		assert!(matches!(skeleton, KinectSkeleton::TrackedExtended(..)));
+		assert_eq!(skeleton.bone(Joint::ThumbRight), Some(&bones[v2::THUMB_RIGHT]));
+
+		let mut table = KinectRetargetTable::KINECT_V2;
+		table.rules[Joint::ThumbRight.sensor_bone()] = None;
+		let skeleton = table.retarget(&bones, &states, None).unwrap();
+		assert!(matches!(skeleton, KinectSkeleton::Tracked(..)));
+		assert_eq!(skeleton.bone(Joint::Head), Some(&bones[v2::HEAD]));
+	}
+
+	#[test]
+	fn too_little_data() {
+		let (bones, states) = v2_frame();
+		let table = KinectRetargetTable::KINECT_V2;
+
+		assert!(table.retarget(&bones[..24], &states, None).is_none());
+		assert!(table.retarget(&bones, &states[..24], None).is_none());
+		assert!(table.retarget(&bones, &states, Some(&[[0.0, 0.0, 0.0, 1.0]; 24])).is_none());
+	}
+}
@@ -0,0 +1,547 @@
+use crate::{math, Joint, KinectBody, KinectBodyId, KinectGestureEvent, KinectSkeleton, KinectTrackingState};
+use std::{path::Path, time::Duration};
+
+/// Where Garry's Mod keeps the [`KinectRules`].
+pub const RULES_PATH: &str = "garrysmod/data/rekinect/rules.txt";
+
+/// How one joint has to be positioned relative to another, from the player's point of view while facing the sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KinectRelation {
+	Above,
+	Below,
+	LeftOf,
+	RightOf,
+	InFrontOf,
+	Behind,
+	/// Within the distance.
+	Near,
+	/// Further than the distance.
+	FarFrom,
+}
+impl KinectRelation {
+	const ALL: [Self; 8] = [
+		Self::Above,
+		Self::Below,
+		Self::LeftOf,
+		Self::RightOf,
+		Self::InFrontOf,
+		Self::Behind,
+		Self::Near,
+		Self::FarFrom,
+	];
+
+	#[inline]
+	pub const fn name(self) -> &'static str {
+		match self {
+			KinectRelation::Above => "above",
+			KinectRelation::Below => "below",
+			KinectRelation::LeftOf => "left_of",
+			KinectRelation::RightOf => "right_of",
+			KinectRelation::InFrontOf => "in_front_of",
+			KinectRelation::Behind => "behind",
+			KinectRelation::Near => "near",
+			KinectRelation::FarFrom => "far_from",
+		}
+	}
+
+	/// Looks up a relation by its [`name`](Self::name), ignoring case.
+	#[inline]
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.into_iter().find(|relation| relation.name().eq_ignore_ascii_case(name))
+	}
+}
+
+/// `joint` is `relation` to `other`, by at least `distance` metres, or within it for [`KinectRelation::Near`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectPredicate {
+	pub joint: Joint,
+	pub relation: KinectRelation,
+	pub other: Joint,
+	pub distance: f32,
+}
+impl KinectPredicate {
+	/// Always false unless both joints are tracked by the sensor, rather than inferred by it or synthesized.
+	///
+	/// Sensor space has x to the sensor's left, which is the player's right, and z away from the sensor.
+	fn holds(&self, skeleton: &KinectSkeleton) -> bool {
+		let tracked = |joint| skeleton.tracking_state(joint) == KinectTrackingState::Tracked;
+		if !tracked(self.joint) || !tracked(self.other) {
+			return false;
+		}
+
+		let (Some(&a), Some(&b)) = (skeleton.bone(self.joint), skeleton.bone(self.other)) else {
+			return false;
+		};
+
+		let d = math::sub(a, b);
+		match self.relation {
+			KinectRelation::Above => d[1] > self.distance,
+			KinectRelation::Below => -d[1] > self.distance,
+			KinectRelation::LeftOf => -d[0] > self.distance,
+			KinectRelation::RightOf => d[0] > self.distance,
+			KinectRelation::InFrontOf => -d[2] > self.distance,
+			KinectRelation::Behind => d[2] > self.distance,
+			KinectRelation::Near => math::length(d) < self.distance,
+			KinectRelation::FarFrom => math::length(d) > self.distance,
+		}
+	}
+}
+
+/// A pose that counts once all of its predicates have held for `hold`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KinectPoseRule {
+	pub name: String,
+	pub predicates: Vec<KinectPredicate>,
+	pub hold: Duration,
+}
+
+/// A gesture made of poses struck one after another, each within `within` of the last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KinectSequenceRule {
+	pub name: String,
+	/// [`KinectPoseRule::name`]s, in order.
+	pub steps: Vec<String>,
+	pub within: Duration,
+}
+
+/// Poses and gestures written as rules rather than recorded, see [`Kinect::set_rules`](crate::Kinect::set_rules).
+///
+/// Rules are read from a text file, one per block:
+///
+/// ```text
+/// # Every predicate has to hold, for 0.5 seconds here. Distances are in metres and default to 0.
+/// pose hands_up hold 0.5
+/// HAND_RIGHT above HEAD 0.1
+/// HAND_LEFT above HEAD 0.1
+///
+/// pose clap
+/// HAND_LEFT near HAND_RIGHT 0.1
+///
+/// # Each pose has to be struck within 1 second of the last. Unlike hold, within is required
+/// sequence celebrate within 1
+/// clap
+/// hands_up
+/// ```
+///
+/// Joints are `SENSORBONE` names, and relations are `above`, `below`, `left_of`, `right_of`, `in_front_of`, `behind`, `near` or `far_from`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KinectRules {
+	pub poses: Vec<KinectPoseRule>,
+	pub sequences: Vec<KinectSequenceRule>,
+}
+impl KinectRules {
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+		std::fs::read_to_string(path)?.parse()
+	}
+}
+impl std::str::FromStr for KinectRules {
+	type Err = std::io::Error;
+
+	/// Blank lines and lines starting with `#` are ignored. Sequences may only use poses defined in the same file, and need a non-zero
+	/// `within`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = |line: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Invalid rule line: {line:?}"));
+
+		let seconds = |line: &str, keyword: &str, words: &[&str]| match words {
+			[] => Ok(Duration::ZERO),
+			[word, seconds] if word.eq_ignore_ascii_case(keyword) => seconds
+				.parse::<f64>()
+				.ok()
+				.and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+				.ok_or_else(|| invalid(line)),
+			_ => Err(invalid(line)),
+		};
+
+		let mut rules = Self::default();
+
+		// Whether the current block is a sequence, or `None` before the first block
+		let mut sequence = None;
+
+		for line in s.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+			let words = line.split_whitespace().collect::<Vec<_>>();
+
+			match words.as_slice() {
+				["pose", name, rest @ ..] => {
+					rules.poses.push(KinectPoseRule {
+						name: name.to_string(),
+						predicates: Vec::new(),
+						hold: seconds(line, "hold", rest)?,
+					});
+					sequence = Some(false);
+				}
+
+				// Without a time limit, every step after the first would be too late
+				["sequence", name, rest @ ..] => {
+					rules.sequences.push(KinectSequenceRule {
+						name: name.to_string(),
+						steps: Vec::new(),
+						within: Some(seconds(line, "within", rest)?)
+							.filter(|within| !within.is_zero())
+							.ok_or_else(|| invalid(line))?,
+					});
+					sequence = Some(true);
+				}
+
+				[step] if sequence == Some(true) => {
+					if !rules.poses.iter().any(|pose| pose.name == *step) {
+						return Err(invalid(line));
+					}
+					rules.sequences.last_mut().unwrap().steps.push(step.to_string());
+				}
+
+				[joint, relation, other, rest @ ..] if sequence == Some(false) && rest.len() <= 1 => {
+					let predicate = KinectPredicate {
+						joint: Joint::from_name(joint).ok_or_else(|| invalid(line))?,
+						relation: KinectRelation::from_name(relation).ok_or_else(|| invalid(line))?,
+						other: Joint::from_name(other).ok_or_else(|| invalid(line))?,
+						distance: match rest.first() {
+							Some(distance) => distance
+								.parse::<f32>()
+								.ok()
+								.filter(|distance| distance.is_finite())
+								.ok_or_else(|| invalid(line))?,
+							None => 0.0,
+						},
+					};
+					rules.poses.last_mut().unwrap().predicates.push(predicate);
+				}
+
+				_ => return Err(invalid(line)),
+			}
+		}
+
+		if let Some(pose) = rules.poses.iter().find(|pose| pose.predicates.is_empty()) {
+			return Err(invalid(&format!("pose {}", pose.name)));
+		}
+		if let Some(sequence) = rules.sequences.iter().find(|sequence| sequence.steps.is_empty()) {
+			return Err(invalid(&format!("sequence {}", sequence.name)));
+		}
+
+		Ok(rules)
+	}
+}
+
+/// A pose was struck or released, see [`Kinect::take_poses`](crate::Kinect::take_poses).
+#[derive(Clone, Debug, PartialEq)]
+pub struct KinectPoseEvent {
+	pub body: KinectBodyId,
+	/// The [`KinectPoseRule::name`].
+	pub name: String,
+	/// Whether the pose is now being held.
+	pub held: bool,
+	/// The [`KinectFrame::timestamp`](crate::KinectFrame::timestamp) of the frame it changed in.
+	pub timestamp: Duration,
+}
+
+struct BodyRules {
+	timestamp: Duration,
+	/// When each pose's predicates started holding.
+	since: Vec<Option<Duration>>,
+	held: Vec<bool>,
+	/// How many steps of each sequence are done, and when the last one was.
+	progress: Vec<(usize, Duration)>,
+}
+
+/// The rules and every body's progress through them.
+#[derive(Default)]
+pub(crate) struct KinectRuleEvaluator {
+	rules: KinectRules,
+	pub(crate) events: Vec<KinectPoseEvent>,
+	bodies: Vec<(KinectBodyId, Box<BodyRules>)>,
+}
+impl KinectRuleEvaluator {
+	/// Events nobody takes are dropped after this many, oldest first.
+	const MAX_EVENTS: usize = 64;
+
+	#[inline]
+	pub(crate) fn rules(&self) -> &KinectRules {
+		&self.rules
+	}
+
+	/// Releases every pose and forgets every body's progress, as it refers to the old rules.
+	#[inline]
+	pub(crate) fn set_rules(&mut self, rules: KinectRules) {
+		self.reset();
+		self.rules = rules;
+	}
+
+	/// Completed sequences are passed to `gesture`.
+	pub(crate) fn apply(&mut self, body: &KinectBody, mut gesture: impl FnMut(KinectGestureEvent)) {
+		let index = self.bodies.iter().position(|(id, _)| *id == body.id);
+
+		if matches!(body.frame.skeleton, KinectSkeleton::Untracked) {
+			if let Some(index) = index {
+				let (id, state) = self.bodies.swap_remove(index);
+				self.release(id, &state, body.frame.timestamp);
+			}
+			return;
+		}
+
+		if self.rules.poses.is_empty() {
+			return;
+		}
+
+		let state = match index {
+			Some(index) => &mut self.bodies[index].1,
+			None => {
+				self.bodies.push((
+					body.id,
+					Box::new(BodyRules {
+						timestamp: body.frame.timestamp,
+						since: vec![None; self.rules.poses.len()],
+						held: vec![false; self.rules.poses.len()],
+						progress: vec![(0, Duration::ZERO); self.rules.sequences.len()],
+					}),
+				));
+				&mut self.bodies.last_mut().unwrap().1
+			}
+		};
+
+		let timestamp = body.frame.timestamp;
+		state.timestamp = timestamp;
+
+		for (i, pose) in self.rules.poses.iter().enumerate() {
+			let holds = pose.predicates.iter().all(|predicate| predicate.holds(&body.frame.skeleton));
+
+			let since = match (holds, state.since[i]) {
+				(false, _) => None,
+				(true, Some(since)) => Some(since),
+				(true, None) => Some(timestamp),
+			};
+			state.since[i] = since;
+
+			let held = since.is_some_and(|since| timestamp.saturating_sub(since) >= pose.hold);
+			if held == state.held[i] {
+				continue;
+			}
+			state.held[i] = held;
+
+			Self::push(&mut self.events, pose, body.id, held, timestamp);
+
+			if !held {
+				continue;
+			}
+
+			for (sequence, (done, last)) in self.rules.sequences.iter().zip(state.progress.iter_mut()) {
+				if *done > 0 && timestamp.saturating_sub(*last) > sequence.within {
+					*done = 0;
+				}
+
+				if sequence.steps.get(*done) == Some(&pose.name) {
+					*done += 1;
+				} else if sequence.steps.first() == Some(&pose.name) {
+					*done = 1;
+				} else {
+					continue;
+				}
+				*last = timestamp;
+
+				if *done == sequence.steps.len() {
+					*done = 0;
+					gesture(KinectGestureEvent {
+						body: body.id,
+						name: sequence.name.clone(),
+						confidence: 1.0,
+						timestamp,
+					});
+				}
+			}
+		}
+	}
+
+	fn push(events: &mut Vec<KinectPoseEvent>, pose: &KinectPoseRule, body: KinectBodyId, held: bool, timestamp: Duration) {
+		if events.len() >= Self::MAX_EVENTS {
+			events.remove(0);
+		}
+		events.push(KinectPoseEvent {
+			body,
+			name: pose.name.clone(),
+			held,
+			timestamp,
+		});
+	}
+
+	/// Nobody can hold a pose without a body.
+	fn release(&mut self, body: KinectBodyId, state: &BodyRules, timestamp: Duration) {
+		for (pose, held) in self.rules.poses.iter().zip(&state.held) {
+			if *held {
+				Self::push(&mut self.events, pose, body, false, timestamp);
+			}
+		}
+	}
+
+	/// Releases every pose and forgets every body's progress, e.g. when switching backends.
+	pub(crate) fn reset(&mut self) {
+		for (body, state) in std::mem::take(&mut self.bodies) {
+			self.release(body, &state, state.timestamp);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::testing;
+
+	/// The rules from [`KinectRules`]'s documentation.
+	const EXAMPLE: &str = "
+# Every predicate has to hold, for 0.5 seconds here. Distances are in metres and default to 0.
+pose hands_up hold 0.5
+HAND_RIGHT above HEAD 0.1
+HAND_LEFT above HEAD 0.1
+
+pose clap
+HAND_LEFT near HAND_RIGHT 0.1
+
+# Each pose has to be struck within 1 second of the last. Unlike hold, within is required
+sequence celebrate within 1
+clap
+hands_up
+";
+
+	/// Hands at `left` and `right`, relative to the head.
+	fn skeleton(left: [f32; 3], right: [f32; 3], state: KinectTrackingState) -> KinectSkeleton {
+		let head = [0.0, 1.6, 2.0];
+		testing::skeleton(
+			&[
+				(Joint::Head, head),
+				(Joint::HandLeft, math::add(head, left)),
+				(Joint::HandRight, math::add(head, right)),
+			],
+			state,
+		)
+	}
+
+	fn hands_up() -> KinectSkeleton {
+		skeleton([0.2, 0.3, 0.0], [-0.2, 0.3, 0.0], KinectTrackingState::Tracked)
+	}
+
+	fn clap() -> KinectSkeleton {
+		skeleton([0.02, -0.5, -0.3], [-0.02, -0.5, -0.3], KinectTrackingState::Tracked)
+	}
+
+	fn rest() -> KinectSkeleton {
+		skeleton([0.3, -0.8, 0.0], [-0.3, -0.8, 0.0], KinectTrackingState::Tracked)
+	}
+
+	/// `frames` frames of `skeleton`.
+	fn hold(frames: usize, skeleton: KinectSkeleton) -> impl Iterator<Item = KinectSkeleton> {
+		std::iter::repeat(skeleton).take(frames)
+	}
+
+	fn evaluate(skeletons: impl IntoIterator<Item = KinectSkeleton>) -> (Vec<KinectPoseEvent>, Vec<KinectGestureEvent>) {
+		let mut evaluator = KinectRuleEvaluator::default();
+		evaluator.set_rules(EXAMPLE.parse().unwrap());
+
+		let mut gestures = Vec::new();
+		for (skeleton, sequence) in skeletons.into_iter().zip(0..) {
+			evaluator.apply(&testing::body(skeleton, sequence), |gesture| gestures.push(gesture));
+		}
+
+		(evaluator.events, gestures)
+	}
+
+	#[test]
+	fn parses_the_example() {
+		let rules = EXAMPLE.parse::<KinectRules>().unwrap();
+
+		assert_eq!(
+			rules.poses,
+			[
+				KinectPoseRule {
+					name: "hands_up".to_string(),
+					predicates: vec![
+						KinectPredicate {
+							joint: Joint::HandRight,
+							relation: KinectRelation::Above,
+							other: Joint::Head,
+							distance: 0.1,
+						},
+						KinectPredicate {
+							joint: Joint::HandLeft,
+							relation: KinectRelation::Above,
+							other: Joint::Head,
+							distance: 0.1,
+						},
+					],
+					hold: Duration::from_millis(500),
+				},
+				KinectPoseRule {
+					name: "clap".to_string(),
+					predicates: vec![KinectPredicate {
+						joint: Joint::HandLeft,
+						relation: KinectRelation::Near,
+						other: Joint::HandRight,
+						distance: 0.1,
+					}],
+					hold: Duration::ZERO,
+				},
+			]
+		);
+
+		assert_eq!(
+			rules.sequences,
+			[KinectSequenceRule {
+				name: "celebrate".to_string(),
+				steps: vec!["clap".to_string(), "hands_up".to_string()],
+				within: Duration::from_secs(1),
+			}]
+		);
+	}
+
+	#[test]
+	fn rejects_bad_rules() {
+		for rules in [
+			"pose p\nNOT_A_JOINT above HEAD\n",
+			"pose p\nHAND_LEFT beside HEAD\n",
+			"pose p\nHAND_LEFT above HEAD\nsequence s within 1\nq\n",
+			"pose p\n",
+			"pose p\nHAND_LEFT above HEAD\nsequence s within 1\n",
+			"HAND_LEFT above HEAD\n",
+			"pose p hold forever\nHAND_LEFT above HEAD\n",
+			"pose p\nHAND_LEFT above HEAD\nsequence s\np\n",
+			"pose p\nHAND_LEFT above HEAD\nsequence s within 0\np\n",
+		] {
+			assert!(rules.parse::<KinectRules>().is_err(), "{rules:?}");
+		}
+	}
+
+	#[test]
+	fn holds_for_hold_before_raising() {
+		let (events, _) = evaluate(hold(30, hands_up()).chain(hold(6, rest())));
+
+		let held = events
+			.iter()
+			.filter(|event| event.name == "hands_up")
+			.map(|event| (event.held, event.timestamp))
+			.collect::<Vec<_>>();
+		assert_eq!(held, [(true, Duration::from_millis(500)), (false, Duration::from_millis(1000))]);
+	}
+
+	#[test]
+	fn only_tracked_joints_count() {
+		let inferred = skeleton([0.2, 0.3, 0.0], [-0.2, 0.3, 0.0], KinectTrackingState::Inferred);
+		let synthesized = skeleton([0.2, 0.3, 0.0], [-0.2, 0.3, 0.0], KinectTrackingState::Synthesized);
+
+		let (events, _) = evaluate(hold(30, inferred).chain(hold(30, synthesized)));
+		assert!(events.is_empty(), "{events:?}");
+	}
+
+	#[test]
+	fn completes_a_sequence() {
+		let (_, gestures) = evaluate(hold(6, clap()).chain(hold(24, hands_up())));
+
+		assert_eq!(gestures.len(), 1, "{gestures:?}");
+		assert_eq!(gestures[0].name, "celebrate");
+		assert_eq!(gestures[0].timestamp, Duration::from_millis(700));
+	}
+
+	#[test]
+	fn sequence_times_out_after_within() {
+		// hands_up is only held 0.5 seconds after it's struck, 1.1 seconds after the clap
+		let (events, gestures) = evaluate(hold(3, clap()).chain(hold(15, rest())).chain(hold(27, hands_up())));
+
+		assert!(events.iter().any(|event| event.name == "hands_up" && event.held), "{events:?}");
+		assert!(gestures.is_empty(), "{gestures:?}");
+	}
+}
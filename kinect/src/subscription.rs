@@ -0,0 +1,258 @@
+use crate::KinectBody;
+use std::{
+	collections::VecDeque,
+	sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+	time::Duration,
+};
+
+/// What a [`KinectSubscription`] does when its reader falls behind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KinectBackpressure {
+	/// Queue every body update, however many pile up.
+	Unbounded,
+	/// Queue at most this many body updates, dropping the oldest to make room.
+	DropOldest(usize),
+	/// Queue at most this many body updates, dropping new ones until there is room.
+	DropNewest(usize),
+	/// Queue at most this many body updates, blocking [`Kinect::poll`](crate::Kinect::poll) until there is room.
+	Block(usize),
+}
+impl Default for KinectBackpressure {
+	#[inline]
+	fn default() -> Self {
+		Self::DropOldest(64)
+	}
+}
+
+/// Identifies a subscription registered with [`Kinect::subscribe`](crate::Kinect::subscribe) or
+/// [`Kinect::subscribe_channel`](crate::Kinect::subscribe_channel).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KinectSubscriptionId(u64);
+
+#[derive(Default)]
+struct KinectQueueState {
+	bodies: VecDeque<KinectBody>,
+	/// Set when either end goes away
+	closed: bool,
+	dropped: u64,
+}
+
+struct KinectQueue {
+	state: Mutex<KinectQueueState>,
+	backpressure: KinectBackpressure,
+	not_empty: Condvar,
+	not_full: Condvar,
+}
+impl KinectQueue {
+	#[inline]
+	fn lock(&self) -> MutexGuard<'_, KinectQueueState> {
+		self.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Returns `false` if the receiver is gone.
+	fn push(&self, body: KinectBody) -> bool {
+		let mut state = self.lock();
+
+		let capacity = match self.backpressure {
+			KinectBackpressure::Unbounded => usize::MAX,
+			KinectBackpressure::DropOldest(capacity) | KinectBackpressure::DropNewest(capacity) | KinectBackpressure::Block(capacity) => {
+				capacity.max(1)
+			}
+		};
+
+		if let KinectBackpressure::Block(_) = self.backpressure {
+			state = self
+				.not_full
+				.wait_while(state, |state| !state.closed && state.bodies.len() >= capacity)
+				.unwrap_or_else(PoisonError::into_inner);
+		}
+
+		if state.closed {
+			return false;
+		}
+
+		if state.bodies.len() >= capacity {
+			state.dropped += 1;
+
+			match self.backpressure {
+				KinectBackpressure::DropNewest(_) => return true,
+				_ => {
+					state.bodies.pop_front();
+				}
+			}
+		}
+
+		state.bodies.push_back(body);
+		self.not_empty.notify_one();
+
+		true
+	}
+
+	fn close(&self) {
+		self.lock().closed = true;
+		self.not_empty.notify_all();
+		self.not_full.notify_all();
+	}
+}
+
+/// Receives every body update from the active backend, see [`Kinect::subscribe_channel`](crate::Kinect::subscribe_channel).
+///
+/// Iterating blocks until the next update arrives, and ends once the [`Kinect`](crate::Kinect) is dropped or the subscription is cancelled.
+pub struct KinectSubscription {
+	id: KinectSubscriptionId,
+	queue: Arc<KinectQueue>,
+}
+impl KinectSubscription {
+	#[inline]
+	pub fn id(&self) -> KinectSubscriptionId {
+		self.id
+	}
+
+	fn pop(&self, mut state: MutexGuard<'_, KinectQueueState>) -> Option<KinectBody> {
+		let body = state.bodies.pop_front();
+		if body.is_some() {
+			self.queue.not_full.notify_one();
+		}
+		body
+	}
+
+	/// Blocks until the next body update. Returns `None` once nothing more will arrive.
+	pub fn recv(&self) -> Option<KinectBody> {
+		let state = self
+			.queue
+			.not_empty
+			.wait_while(self.queue.lock(), |state| !state.closed && state.bodies.is_empty())
+			.unwrap_or_else(PoisonError::into_inner);
+
+		self.pop(state)
+	}
+
+	/// Like [`KinectSubscription::recv`], but gives up after `timeout`.
+	pub fn recv_timeout(&self, timeout: Duration) -> Option<KinectBody> {
+		let (state, _) = self
+			.queue
+			.not_empty
+			.wait_timeout_while(self.queue.lock(), timeout, |state| !state.closed && state.bodies.is_empty())
+			.unwrap_or_else(PoisonError::into_inner);
+
+		self.pop(state)
+	}
+
+	#[inline]
+	pub fn try_recv(&self) -> Option<KinectBody> {
+		self.pop(self.queue.lock())
+	}
+
+	/// How many body updates backpressure has thrown away so far.
+	#[inline]
+	pub fn dropped(&self) -> u64 {
+		self.queue.lock().dropped
+	}
+
+	/// `true` once the [`Kinect`](crate::Kinect) has gone away or the subscription was cancelled. Queued updates can still be received.
+	#[inline]
+	pub fn is_closed(&self) -> bool {
+		self.queue.lock().closed
+	}
+}
+impl Iterator for KinectSubscription {
+	type Item = KinectBody;
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.recv()
+	}
+}
+impl Drop for KinectSubscription {
+	#[inline]
+	fn drop(&mut self) {
+		self.queue.close();
+	}
+}
+impl std::fmt::Debug for KinectSubscription {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("KinectSubscription")
+			.field("id", &self.id)
+			.field("backpressure", &self.queue.backpressure)
+			.finish_non_exhaustive()
+	}
+}
+
+enum KinectSubscriberKind {
+	Callback(Box<dyn FnMut(&KinectBody) + Send>),
+	Channel(Arc<KinectQueue>),
+}
+
+/// The [`Kinect`](crate::Kinect) end of a subscription.
+pub(crate) struct KinectSubscribers {
+	subscribers: Vec<(KinectSubscriptionId, KinectSubscriberKind)>,
+	next_id: u64,
+}
+impl KinectSubscribers {
+	#[inline]
+	pub(crate) const fn new() -> Self {
+		Self {
+			subscribers: Vec::new(),
+			next_id: 0,
+		}
+	}
+
+	fn next_id(&mut self) -> KinectSubscriptionId {
+		self.next_id += 1;
+		KinectSubscriptionId(self.next_id)
+	}
+
+	pub(crate) fn subscribe(&mut self, callback: Box<dyn FnMut(&KinectBody) + Send>) -> KinectSubscriptionId {
+		let id = self.next_id();
+		self.subscribers.push((id, KinectSubscriberKind::Callback(callback)));
+		id
+	}
+
+	pub(crate) fn subscribe_channel(&mut self, backpressure: KinectBackpressure) -> KinectSubscription {
+		let id = self.next_id();
+
+		let queue = Arc::new(KinectQueue {
+			state: Mutex::default(),
+			backpressure,
+			not_empty: Condvar::new(),
+			not_full: Condvar::new(),
+		});
+
+		self.subscribers.push((id, KinectSubscriberKind::Channel(queue.clone())));
+
+		KinectSubscription { id, queue }
+	}
+
+	/// Returns `false` if there was no such subscription.
+	pub(crate) fn unsubscribe(&mut self, id: KinectSubscriptionId) -> bool {
+		let Some(index) = self.subscribers.iter().position(|(subscriber, _)| *subscriber == id) else {
+			return false;
+		};
+
+		if let (_, KinectSubscriberKind::Channel(queue)) = self.subscribers.remove(index) {
+			queue.close();
+		}
+
+		true
+	}
+
+	pub(crate) fn publish(&mut self, body: &KinectBody) {
+		self.subscribers.retain_mut(|(_, subscriber)| match subscriber {
+			KinectSubscriberKind::Callback(callback) => {
+				callback(body);
+				true
+			}
+
+			KinectSubscriberKind::Channel(queue) => queue.push(*body),
+		});
+	}
+}
+impl Drop for KinectSubscribers {
+	fn drop(&mut self) {
+		for (_, subscriber) in &self.subscribers {
+			if let KinectSubscriberKind::Channel(queue) = subscriber {
+				queue.close();
+			}
+		}
+	}
+}
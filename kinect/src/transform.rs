@@ -0,0 +1,143 @@
+use crate::{math, KinectSkeleton};
+
+/// Hammer units are 0.75 inches.
+pub const HAMMER_UNITS_PER_METRE: f32 = 1.0 / 0.01905;
+
+/// An axis of sensor space, optionally flipped.
+///
+/// Sensor space is right-handed and in metres, with x pointing to the sensor's left, y pointing up and z pointing away from the sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KinectAxis {
+	X,
+	Y,
+	Z,
+	NegX,
+	NegY,
+	NegZ,
+}
+impl KinectAxis {
+	#[inline]
+	const fn index(self) -> usize {
+		match self {
+			KinectAxis::X | KinectAxis::NegX => 0,
+			KinectAxis::Y | KinectAxis::NegY => 1,
+			KinectAxis::Z | KinectAxis::NegZ => 2,
+		}
+	}
+
+	#[inline]
+	const fn sign(self) -> f32 {
+		match self {
+			KinectAxis::X | KinectAxis::Y | KinectAxis::Z => 1.0,
+			KinectAxis::NegX | KinectAxis::NegY | KinectAxis::NegZ => -1.0,
+		}
+	}
+}
+
+/// Takes sensor space to the coordinate space [`Kinect`](crate::Kinect) reports bodies in.
+///
+/// Mirroring happens first, then the axes are mapped, then everything is scaled, and finally the offset is added.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectTransform {
+	/// The sensor axis each output axis takes its value from. Mapping to a space of the other handedness is fine, orientations are
+	/// converted to match.
+	pub axes: [KinectAxis; 3],
+	/// E.g. [`HAMMER_UNITS_PER_METRE`].
+	pub scale: f32,
+	/// Reflect the skeleton left to right like a mirror image, so that the player's left hand drives the skeleton's right hand.
+	pub mirror: bool,
+	/// Added last, in output units.
+	pub offset: [f32; 3],
+}
+impl KinectTransform {
+	/// Leaves everything in sensor space.
+	pub const SENSOR: Self = Self {
+		axes: [KinectAxis::X, KinectAxis::Y, KinectAxis::Z],
+		scale: 1.0,
+		mirror: false,
+		offset: [0.0; 3],
+	};
+
+	/// x to the sensor's right, y away from the sensor and z up, in metres, which is what `motionsensor` expects.
+	pub const GMOD: Self = Self {
+		axes: [KinectAxis::NegX, KinectAxis::Z, KinectAxis::Y],
+		..Self::SENSOR
+	};
+
+	#[inline]
+	pub const fn with_scale(mut self, scale: f32) -> Self {
+		self.scale = scale;
+		self
+	}
+
+	#[inline]
+	pub const fn with_mirror(mut self, mirror: bool) -> Self {
+		self.mirror = mirror;
+		self
+	}
+
+	#[inline]
+	pub const fn with_offset(mut self, offset: [f32; 3]) -> Self {
+		self.offset = offset;
+		self
+	}
+
+	#[inline]
+	fn map_axes(&self, vector: [f32; 3]) -> [f32; 3] {
+		let mirror = if self.mirror { -1.0 } else { 1.0 };
+		let vector = [vector[0] * mirror, vector[1], vector[2]];
+		self.axes.map(|axis| vector[axis.index()] * axis.sign())
+	}
+
+	/// `1.0` if the transform keeps the handedness of sensor space, `-1.0` if it flips it.
+	pub fn handedness(&self) -> f32 {
+		let [x, y, z] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]].map(|axis| self.map_axes(axis));
+		math::dot(math::cross(x, y), z).signum()
+	}
+
+	/// Transforms a position from sensor space.
+	#[inline]
+	pub fn point(&self, point: [f32; 3]) -> [f32; 3] {
+		math::add(math::scale(self.map_axes(point), self.scale), self.offset)
+	}
+
+	/// Transforms an `[x, y, z, w]` orientation from sensor space.
+	#[inline]
+	pub fn orientation(&self, orientation: [f32; 4]) -> [f32; 4] {
+		// The vector part of a quaternion is a pseudovector, so it flips along with the handedness
+		let [x, y, z] = math::scale(self.map_axes([orientation[0], orientation[1], orientation[2]]), self.handedness());
+		[x, y, z, orientation[3]]
+	}
+
+	pub(crate) fn apply(&self, skeleton: &mut KinectSkeleton) {
+		let orientation = |orientation: &mut [f32; 4]| *orientation = self.orientation(*orientation);
+
+		if let Some(skeleton) = skeleton.tracked_mut() {
+			skeleton.raw_bones_mut().iter_mut().for_each(|bone| *bone = self.point(*bone));
+			skeleton.orientations_mut().into_iter().flatten().for_each(orientation);
+
+			if self.mirror {
+				skeleton.swap_sides();
+
+				if let Some(hands) = skeleton.hands_mut() {
+					std::mem::swap(&mut hands.left, &mut hands.right);
+				}
+			}
+		}
+
+		if let Some(extended) = skeleton.extended_mut() {
+			extended.raw_bones_mut().iter_mut().for_each(|bone| *bone = self.point(*bone));
+			extended.orientations_mut().into_iter().flatten().for_each(orientation);
+
+			if self.mirror {
+				extended.swap_sides();
+			}
+		}
+	}
+}
+impl Default for KinectTransform {
+	#[inline]
+	fn default() -> Self {
+		Self::GMOD
+	}
+}
@@ -0,0 +1,321 @@
+use crate::{
+	math::{self, Vec3},
+	Joint, KinectBody, KinectBodyId, KinectSkeleton, KinectTrackingState,
+};
+use std::time::Duration;
+
+/// How [`Kinect`](crate::Kinect) catches sensor glitches before they reach anything else.
+///
+/// Every joint is checked in order, from the hips outwards:
+///
+/// * NaN or infinite positions are replaced by the joint's last good position.
+/// * Joints moving faster than `max_speed` since they were last seen are held where they were, for up to `max_rejected_frames` frames
+///   in a row.
+/// * Joints further from (or closer to) their [`Joint::parent`] than the bone length learned so far allows are pulled back onto it.
+///
+/// Joints that were changed are reported as [`KinectTrackingState::Inferred`], or [`KinectTrackingState::NotTracked`] if there was nothing
+/// to replace them with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KinectValidation {
+	/// In metres per second. Hands can move at around 10 m/s when punching.
+	pub max_speed: f32,
+	/// How far a bone's length may stray from the learned length, as a fraction of it.
+	pub length_tolerance: f32,
+	/// How many tracked frames of a bone to see before enforcing its length. Later frames keep refining it.
+	pub learning_frames: u32,
+	/// After this many rejected frames in a row, the joint is assumed to really be there.
+	pub max_rejected_frames: u32,
+}
+impl Default for KinectValidation {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			max_speed: 15.0,
+			length_tolerance: 0.3,
+			learning_frames: 30,
+			max_rejected_frames: 5,
+		}
+	}
+}
+impl KinectValidation {
+	#[inline]
+	pub const fn with_max_speed(mut self, max_speed: f32) -> Self {
+		self.max_speed = max_speed;
+		self
+	}
+
+	#[inline]
+	pub const fn with_length_tolerance(mut self, length_tolerance: f32) -> Self {
+		self.length_tolerance = length_tolerance;
+		self
+	}
+
+	#[inline]
+	pub const fn with_learning_frames(mut self, learning_frames: u32) -> Self {
+		self.learning_frames = learning_frames;
+		self
+	}
+
+	#[inline]
+	pub const fn with_max_rejected_frames(mut self, max_rejected_frames: u32) -> Self {
+		self.max_rejected_frames = max_rejected_frames;
+		self
+	}
+}
+
+/// The learned bone lengths only ever average over this many frames, so they can still adapt.
+const LENGTH_WINDOW: u32 = 300;
+
+/// Assume 30 FPS if the backend doesn't have sensible timestamps.
+const FALLBACK_FRAME_TIME: f32 = 1.0 / 30.0;
+
+struct BodyValidation {
+	/// Each joint's last good position, and the timestamp of the frame it was in. Joints can go untracked for a while, so how far they
+	/// may have moved since depends on when they were last seen rather than on the previous frame.
+	last: [Option<(Vec3, Duration)>; Joint::COUNT],
+	rejected: [u32; Joint::COUNT],
+	lengths: [f32; Joint::COUNT],
+	samples: [u32; Joint::COUNT],
+}
+impl Default for BodyValidation {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			last: [None; Joint::COUNT],
+			rejected: [0; Joint::COUNT],
+			lengths: [0.0; Joint::COUNT],
+			samples: [0; Joint::COUNT],
+		}
+	}
+}
+impl BodyValidation {
+	fn apply(&mut self, validation: &KinectValidation, skeleton: &mut KinectSkeleton, timestamp: Duration) {
+		for joint in Joint::ALL {
+			let Some(&position) = skeleton.bone(joint) else {
+				continue;
+			};
+
+			let index = joint.sensor_bone();
+			let mut tracking_state = skeleton.tracking_state(joint);
+			let mut validated = position;
+			let mut changed = false;
+
+			let finite = position.iter().all(|axis| axis.is_finite());
+			let fallback = || joint.parent().and_then(|parent| skeleton.bone(parent).copied()).unwrap_or_default();
+
+			if tracking_state == KinectTrackingState::NotTracked {
+				// Nobody should be relying on it, so just make sure it's a number
+				if !finite {
+					let fallback = fallback();
+					if let Some(bone) = skeleton.bone_mut(joint) {
+						*bone = fallback;
+					}
+				}
+				continue;
+			}
+
+			if !finite {
+				changed = true;
+				match self.last[index] {
+					Some((last, _)) => validated = last,
+					None => {
+						validated = fallback();
+						tracking_state = KinectTrackingState::NotTracked;
+					}
+				}
+			} else if let Some((last, seen)) = self.last[index] {
+				let dt = match timestamp.checked_sub(seen) {
+					Some(dt) if !dt.is_zero() => dt.as_secs_f32(),
+					_ => FALLBACK_FRAME_TIME,
+				};
+				let too_fast = math::length(math::sub(position, last)) > validation.max_speed * dt;
+
+				if too_fast && self.rejected[index] < validation.max_rejected_frames {
+					self.rejected[index] += 1;
+					validated = last;
+					changed = true;
+				} else {
+					self.rejected[index] = 0;
+				}
+			}
+
+			if let Some(&parent) = joint.parent().and_then(|parent| skeleton.bone(parent)) {
+				let bone = math::sub(validated, parent);
+				let length = math::length(bone);
+
+				if self.samples[index] >= validation.learning_frames {
+					let learned = self.lengths[index];
+					let clamped = length.clamp(
+						learned * (1.0 - validation.length_tolerance),
+						learned * (1.0 + validation.length_tolerance),
+					);
+
+					if clamped != length {
+						if let Some(direction) = math::normalize(bone) {
+							validated = math::add(parent, math::scale(direction, clamped));
+							changed = true;
+						}
+					}
+				}
+
+				if !changed && tracking_state == KinectTrackingState::Tracked {
+					self.samples[index] = self.samples[index].saturating_add(1);
+					self.lengths[index] += (length - self.lengths[index]) / self.samples[index].min(LENGTH_WINDOW) as f32;
+				}
+			}
+
+			if changed {
+				if let Some(bone) = skeleton.bone_mut(joint) {
+					*bone = validated;
+				}
+
+				if tracking_state == KinectTrackingState::Tracked {
+					tracking_state = KinectTrackingState::Inferred;
+				}
+				if let Some(state) = skeleton.tracking_state_mut(joint) {
+					*state = tracking_state;
+				}
+			}
+
+			if tracking_state != KinectTrackingState::NotTracked {
+				self.last[index] = Some((validated, timestamp));
+			}
+		}
+
+		scrub_orientations(skeleton);
+	}
+}
+
+/// Replaces NaN or infinite orientations with the identity.
+fn scrub_orientations(skeleton: &mut KinectSkeleton) {
+	let scrub = |orientation: &mut [f32; 4]| {
+		if !orientation.iter().all(|axis| axis.is_finite()) {
+			*orientation = [0.0, 0.0, 0.0, 1.0];
+		}
+	};
+
+	if let Some(orientations) = skeleton.tracked_mut().and_then(|tracked| tracked.orientations_mut()) {
+		orientations.iter_mut().for_each(scrub);
+	}
+
+	if let Some(orientations) = skeleton.extended_mut().and_then(|extended| extended.orientations_mut()) {
+		orientations.iter_mut().for_each(scrub);
+	}
+}
+
+/// Every body's validation state.
+#[derive(Default)]
+pub(crate) struct KinectValidator {
+	bodies: Vec<(KinectBodyId, Box<BodyValidation>)>,
+}
+impl KinectValidator {
+	pub(crate) fn apply(&mut self, validation: &KinectValidation, body: &mut KinectBody) {
+		let index = self.bodies.iter().position(|(id, _)| *id == body.id);
+
+		if matches!(body.frame.skeleton, KinectSkeleton::Untracked) {
+			if let Some(index) = index {
+				self.bodies.swap_remove(index);
+			}
+			return;
+		}
+
+		let state = match index {
+			Some(index) => &mut self.bodies[index].1,
+			None => {
+				self.bodies.push((body.id, Box::default()));
+				&mut self.bodies.last_mut().unwrap().1
+			}
+		};
+
+		state.apply(validation, &mut body.frame.skeleton, body.frame.timestamp);
+	}
+
+	/// How long this body's bone from `joint` to its parent is, once enough of it has been seen to enforce it.
+	pub(crate) fn bone_length(&self, validation: &KinectValidation, id: KinectBodyId, joint: Joint) -> Option<f32> {
+		let (_, state) = self.bodies.iter().find(|(body, _)| *body == id)?;
+		let index = joint.sensor_bone();
+		Some(state.lengths[index]).filter(|length| state.samples[index] >= validation.learning_frames && *length > 0.0)
+	}
+
+	/// Forgets every body, e.g. when switching backends.
+	#[inline]
+	pub(crate) fn reset(&mut self) {
+		self.bodies.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::testing;
+
+	/// Slow enough that the right hand can't swing to the other side of the wrist in one frame.
+	const VALIDATION: KinectValidation = KinectValidation {
+		max_speed: 2.0,
+		length_tolerance: 0.3,
+		learning_frames: 30,
+		max_rejected_frames: 5,
+	};
+
+	/// The right hand 10cm to either side of the wrist, which is at the origin like every other joint.
+	fn hand(x: f32, tracking_state: KinectTrackingState) -> KinectSkeleton {
+		let mut skeleton = testing::skeleton(&[(Joint::HandRight, [x, 0.0, 0.0])], KinectTrackingState::Tracked);
+		*skeleton.tracking_state_mut(Joint::HandRight).unwrap() = tracking_state;
+		skeleton
+	}
+
+	/// Validates `skeletons` as consecutive frames, returning what became of the right hand in each.
+	fn validate(skeletons: impl IntoIterator<Item = KinectSkeleton>) -> Vec<(Vec3, KinectTrackingState)> {
+		let mut validator = KinectValidator::default();
+		skeletons
+			.into_iter()
+			.zip(0..)
+			.map(|(skeleton, sequence)| {
+				let mut body = testing::body(skeleton, sequence);
+				validator.apply(&VALIDATION, &mut body);
+				let skeleton = body.frame.skeleton;
+				(*skeleton.bone(Joint::HandRight).unwrap(), skeleton.tracking_state(Joint::HandRight))
+			})
+			.collect()
+	}
+
+	fn still() -> impl Iterator<Item = KinectSkeleton> {
+		std::iter::repeat(hand(0.1, KinectTrackingState::Tracked)).take(40)
+	}
+
+	#[test]
+	fn teleporting_joints_are_held() {
+		let hands = validate(still().chain(std::iter::repeat(hand(-0.1, KinectTrackingState::Tracked)).take(6)));
+
+		for &hand in &hands[40..45] {
+			assert_eq!(hand, ([0.1, 0.0, 0.0], KinectTrackingState::Inferred));
+		}
+
+		// Until it's been there for long enough to be believed
+		assert_eq!(hands[45], ([-0.1, 0.0, 0.0], KinectTrackingState::Tracked));
+	}
+
+	#[test]
+	fn occluded_joints_can_reappear_elsewhere() {
+		// A second out of sight is plenty of time to move 20cm
+		let occluded = std::iter::repeat(hand(0.0, KinectTrackingState::NotTracked)).take(30);
+		let hands = validate(still().chain(occluded).chain([hand(-0.1, KinectTrackingState::Tracked)]));
+
+		assert_eq!(hands[70], ([-0.1, 0.0, 0.0], KinectTrackingState::Tracked));
+	}
+
+	#[test]
+	fn bones_are_pulled_back_to_their_learned_length() {
+		let hands = validate(still().chain([hand(0.15, KinectTrackingState::Tracked)]));
+
+		let (position, tracking_state) = hands[40];
+		assert!((position[0] - 0.13).abs() < 1e-5, "{position:?}");
+		assert_eq!(tracking_state, KinectTrackingState::Inferred);
+	}
+
+	#[test]
+	fn non_finite_positions_are_replaced() {
+		let hands = validate(still().chain([hand(f32::NAN, KinectTrackingState::Tracked)]));
+		assert_eq!(hands[40], ([0.1, 0.0, 0.0], KinectTrackingState::Inferred));
+	}
+}